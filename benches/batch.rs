@@ -0,0 +1,44 @@
+// Compares encrypting blocks one by one on a single thread with the batch
+// API spread over the rayon thread pool. Without the `parallel` feature both
+// would run the same sequential loop, so the target requires it:
+// `cargo bench --bench batch --features parallel`.
+use criterion::{BenchmarkId, Criterion, Throughput};
+use franklin_crypto::bellman::pairing::bn256::{Bn256, Fr};
+use rand::{Rand, SeedableRng, XorShiftRng};
+use rescue_block_cipher::batch::PreparedNativeCipher;
+use rescue_block_cipher::cipher_tools::defaults::bn256_cipher_params_3;
+use rescue_block_cipher::native::NativeKeySchedule;
+
+const BATCH_SIZES: [usize; 3] = [64, 1024, 16384];
+
+fn random_blocks(rng: &mut XorShiftRng, count: usize) -> Vec<[Fr; 3]> {
+    (0..count).map(|_| [Fr::rand(rng), Fr::rand(rng), Fr::rand(rng)]).collect()
+}
+
+fn bench_batch_encryption(crit: &mut Criterion) {
+    let rng = &mut XorShiftRng::from_seed([0x5dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+    let params = bn256_cipher_params_3().unwrap();
+    let key = [Fr::rand(rng), Fr::rand(rng), Fr::rand(rng)];
+    let key_schedule = NativeKeySchedule::<Bn256, 3, 29>::new(&params, &key).unwrap();
+    let cipher = PreparedNativeCipher::new(&key_schedule);
+
+    let mut group = crit.benchmark_group("Batch Encryption");
+    for count in BATCH_SIZES.iter() {
+        let blocks = random_blocks(rng, *count);
+        group.throughput(Throughput::Elements(*count as u64));
+
+        group.bench_with_input(BenchmarkId::new("single_thread", count), &blocks, |b, blocks| {
+            b.iter(|| blocks.iter().map(|block| cipher.encrypt(block)).collect::<Vec<_>>())
+        });
+        group.bench_with_input(BenchmarkId::new("parallel", count), &blocks, |b, blocks| {
+            b.iter(|| cipher.encrypt_batch(blocks))
+        });
+    }
+    group.finish();
+}
+
+fn main() {
+    let crit = &mut Criterion::default().configure_from_args();
+    bench_batch_encryption(crit);
+    crit.final_summary();
+}
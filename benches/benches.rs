@@ -0,0 +1,8 @@
+use criterion::Criterion;
+mod gadgets;
+
+fn main() {
+    let crit = &mut Criterion::default().configure_from_args();
+    gadgets::group(crit);
+    crit.final_summary();
+}
@@ -0,0 +1,87 @@
+use criterion::Criterion;
+
+use franklin_crypto::bellman::pairing::bn256::{Bn256, Fr};
+use franklin_crypto::{
+    bellman::plonk::better_better_cs::cs::{TrivialAssembly, Width4MainGateWithDNext},
+    bellman::Engine,
+    plonk::circuit::{allocated_num::Num, Width4WithCustomGates},
+};
+
+use rand::{Rand, SeedableRng, XorShiftRng};
+use rescue_block_cipher::cipher_tools::defaults::{bn256_cipher_params_3, Bn256CipherParams3};
+use rescue_block_cipher::enc_dec::{construct_ready_params, rescue_encryption, KeySchedule};
+use rescue_block_cipher::native::{
+    rescue_decryption_with_key_schedule_native, rescue_encryption_with_key_schedule_native,
+    NativeKeySchedule,
+};
+
+fn init_rng() -> XorShiftRng {
+    const TEST_SEED: [u32; 4] = [0x5dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654];
+    XorShiftRng::from_seed(TEST_SEED)
+}
+fn init_cs<E: Engine>() -> TrivialAssembly<E, Width4WithCustomGates, Width4MainGateWithDNext> {
+    TrivialAssembly::<E, Width4WithCustomGates, Width4MainGateWithDNext>::new()
+}
+
+fn test_block() -> [Fr; 3] {
+    let rng = &mut init_rng();
+    [Fr::rand(rng), Fr::rand(rng), Fr::rand(rng)]
+}
+
+fn alloc_block(cs: &mut TrivialAssembly<Bn256, Width4WithCustomGates, Width4MainGateWithDNext>, block: &[Fr; 3]) -> [Num<Bn256>; 3] {
+    let mut nums = [Num::<Bn256>::zero(); 3];
+    for i in 0..3 {
+        nums[i] = Num::alloc(cs, Some(block[i])).unwrap();
+    }
+    nums
+}
+
+fn bench_native_key_schedule(crit: &mut Criterion) {
+    let params: Bn256CipherParams3 = bn256_cipher_params_3().unwrap();
+    crit.bench_function("Native Key Schedule", |b| {
+        b.iter(|| NativeKeySchedule::new(&params, &test_block()).unwrap());
+    });
+}
+
+fn bench_native_encryption(crit: &mut Criterion) {
+    let params: Bn256CipherParams3 = bn256_cipher_params_3().unwrap();
+    let key_schedule = NativeKeySchedule::new(&params, &test_block()).unwrap();
+    let mut group = crit.benchmark_group("Native Cipher");
+
+    group.bench_function("Encryption", |b| {
+        b.iter(|| rescue_encryption_with_key_schedule_native(&key_schedule, &test_block()));
+    });
+    group.bench_function("Decryption", |b| {
+        b.iter(|| rescue_decryption_with_key_schedule_native(&key_schedule, &test_block()));
+    });
+    group.finish();
+}
+
+fn bench_synthesis(crit: &mut Criterion) {
+    let params: Bn256CipherParams3 = bn256_cipher_params_3().unwrap();
+    let ready_params = construct_ready_params(&params).unwrap();
+    let mut group = crit.benchmark_group("Cipher Synthesis");
+
+    group.bench_function("Key Schedule", |b| {
+        b.iter(|| {
+            let cs = &mut init_cs::<Bn256>();
+            let key = alloc_block(cs, &test_block());
+            KeySchedule::new(cs, &ready_params, &key).unwrap()
+        });
+    });
+    group.bench_function("Encryption", |b| {
+        b.iter(|| {
+            let cs = &mut init_cs::<Bn256>();
+            let key = alloc_block(cs, &test_block());
+            let plaintext = alloc_block(cs, &test_block());
+            rescue_encryption(cs, &ready_params, &key, &plaintext).unwrap()
+        });
+    });
+    group.finish();
+}
+
+pub fn group(crit: &mut Criterion) {
+    bench_native_key_schedule(crit);
+    bench_native_encryption(crit);
+    bench_synthesis(crit);
+}
@@ -90,10 +90,14 @@ impl<E: Engine, const RATE: usize, const WIDTH: usize> HashParams<E, RATE, WIDTH
 }
 
 
+const RESCUE_FULL_ROUNDS: usize = 8;
+const RESCUE_SECURITY_LEVEL: usize = 126;
+const RESCUE_ROUNDS_TAG: &[u8; 8] = b"Rescue_f";
+
 pub(crate) fn compute_params<E: Engine, const RATE: usize, const WIDTH: usize, const N: usize>() -> (InnerHashParameters<E, RATE, WIDTH>, u64, [u64; N]) {
     // let full_rounds = 22;
-    let full_rounds = 8;
-    let security_level = 126;
+    let full_rounds = RESCUE_FULL_ROUNDS;
+    let security_level = RESCUE_SECURITY_LEVEL;
 
     let mut params = InnerHashParameters::new(        
         security_level,
@@ -101,11 +105,10 @@ pub(crate) fn compute_params<E: Engine, const RATE: usize, const WIDTH: usize, c
         0,
     );
 
-    let rounds_tag = b"Rescue_f";
     let _mds_tag = b"ResM0003";
     let total_number_of_rounds = 2*full_rounds + 1;
     
-    params.compute_round_constants(total_number_of_rounds, rounds_tag);
+    params.compute_round_constants(total_number_of_rounds, RESCUE_ROUNDS_TAG);
     params.compute_mds_matrix_for_rescue();
 
     let alpha = 5u64;
@@ -115,3 +118,16 @@ pub(crate) fn compute_params<E: Engine, const RATE: usize, const WIDTH: usize, c
 }
 
 
+
+/// Round constants of the default Rescue parameters for any number of rounds.
+/// All constants are drawn from the same Blake2s stream, so the first
+/// 2 * full_rounds + 1 of them are exactly those of `RescueParams::default()`.
+pub fn compute_round_constants<E: Engine, const RATE: usize, const WIDTH: usize>(number_of_rounds: usize) -> Vec<[E::Fr; WIDTH]> {
+    let mut params = InnerHashParameters::<E, RATE, WIDTH>::new(
+        RESCUE_SECURITY_LEVEL,
+        RESCUE_FULL_ROUNDS,
+        0,
+    );
+    params.compute_round_constants(number_of_rounds, RESCUE_ROUNDS_TAG);
+    params.round_constants
+}
@@ -0,0 +1,320 @@
+use franklin_crypto::plonk::circuit::allocated_num::Num;
+use franklin_crypto::bellman::pairing::Engine;
+use franklin_crypto::bellman::pairing::ff::Field;
+use franklin_crypto::bellman::plonk::better_better_cs::cs::ConstraintSystem;
+use rescue_poseidon::{CircuitGenericSponge, GenericSponge, HashParams};
+use super::cipher_tools::u64_to_fe;
+use super::error::CipherError;
+
+// SpongeWrap-style authenticated encryption over a duplex sponge.
+//
+// The sponge first absorbs the header [key, nonce, len(ad), len(message)]
+// followed by the associated data. Since both lengths are bound in the
+// header, the associated data and the message always start at known
+// positions, which separates the two domains. Every message block is masked
+// by the rate part of the state squeezed after the previous block and then
+// the plaintext block itself is absorbed. The tag is the first element
+// squeezed after the last block.
+pub fn aead_encryption_native<
+    E: Engine,
+    P: HashParams<E, RATE, WIDTH>,
+    const RATE: usize,
+    const WIDTH: usize>(
+    params: &P,
+    key: &[E::Fr],
+    nonce: &E::Fr,
+    associated_data: &[E::Fr],
+    message: &[E::Fr])->Result<(Vec<E::Fr>, E::Fr), CipherError>{
+
+    let mut sponge = GenericSponge::<E, RATE, WIDTH>::new();
+    let mut keystream = absorb_header_native(&mut sponge, params, key, nonce, associated_data, message.len())?;
+
+    let mut ciphertext = Vec::with_capacity(message.len());
+    for chunk in message.chunks(RATE) {
+        for (m, k) in chunk.iter().zip(keystream.iter()) {
+            let mut c = *m;
+            c.add_assign(k);
+            ciphertext.push(c);
+        }
+        keystream = duplex_native(&mut sponge, params, chunk)?;
+    }
+
+    Ok((ciphertext, keystream[0]))
+}
+
+// Returns Ok(None) if the tag does not match the ciphertext and associated data.
+pub fn aead_decryption_native<
+    E: Engine,
+    P: HashParams<E, RATE, WIDTH>,
+    const RATE: usize,
+    const WIDTH: usize>(
+    params: &P,
+    key: &[E::Fr],
+    nonce: &E::Fr,
+    associated_data: &[E::Fr],
+    ciphertext: &[E::Fr],
+    tag: &E::Fr)->Result<Option<Vec<E::Fr>>, CipherError>{
+
+    let mut sponge = GenericSponge::<E, RATE, WIDTH>::new();
+    let mut keystream = absorb_header_native(&mut sponge, params, key, nonce, associated_data, ciphertext.len())?;
+
+    let mut message = Vec::with_capacity(ciphertext.len());
+    for chunk in ciphertext.chunks(RATE) {
+        let start = message.len();
+        for (c, k) in chunk.iter().zip(keystream.iter()) {
+            let mut m = *c;
+            m.sub_assign(k);
+            message.push(m);
+        }
+        keystream = duplex_native(&mut sponge, params, &message[start..])?;
+    }
+
+    if keystream[0] != *tag {
+        return Ok(None);
+    }
+    Ok(Some(message))
+}
+
+pub fn aead_encryption<
+    E: Engine,
+    CS: ConstraintSystem<E>,
+    P: HashParams<E, RATE, WIDTH>,
+    const RATE: usize,
+    const WIDTH: usize>(
+    cs: &mut CS,
+    params: &P,
+    key: &[Num<E>],
+    nonce: &Num<E>,
+    associated_data: &[Num<E>],
+    message: &[Num<E>])->Result<(Vec<Num<E>>, Num<E>), CipherError>{
+
+    let mut sponge = CircuitGenericSponge::<E, RATE, WIDTH>::new();
+    let mut keystream = absorb_header(cs, &mut sponge, params, key, nonce, associated_data, message.len())?;
+
+    let mut ciphertext = Vec::with_capacity(message.len());
+    for chunk in message.chunks(RATE) {
+        for (m, k) in chunk.iter().zip(keystream.iter()) {
+            ciphertext.push(m.add(cs, k)?);
+        }
+        keystream = duplex(cs, &mut sponge, params, chunk)?;
+    }
+
+    Ok((ciphertext, keystream[0]))
+}
+
+// Enforces that the tag matches the ciphertext and associated data.
+pub fn aead_decryption<
+    E: Engine,
+    CS: ConstraintSystem<E>,
+    P: HashParams<E, RATE, WIDTH>,
+    const RATE: usize,
+    const WIDTH: usize>(
+    cs: &mut CS,
+    params: &P,
+    key: &[Num<E>],
+    nonce: &Num<E>,
+    associated_data: &[Num<E>],
+    ciphertext: &[Num<E>],
+    tag: &Num<E>)->Result<Vec<Num<E>>, CipherError>{
+
+    let mut sponge = CircuitGenericSponge::<E, RATE, WIDTH>::new();
+    let mut keystream = absorb_header(cs, &mut sponge, params, key, nonce, associated_data, ciphertext.len())?;
+
+    let mut message = Vec::with_capacity(ciphertext.len());
+    for chunk in ciphertext.chunks(RATE) {
+        let start = message.len();
+        for (c, k) in chunk.iter().zip(keystream.iter()) {
+            message.push(c.sub(cs, k)?);
+        }
+        keystream = duplex(cs, &mut sponge, params, &message[start..])?;
+    }
+
+    tag.enforce_equal(cs, &keystream[0])?;
+
+    Ok(message)
+}
+
+fn absorb_header_native<
+    E: Engine,
+    P: HashParams<E, RATE, WIDTH>,
+    const RATE: usize,
+    const WIDTH: usize>(
+    sponge: &mut GenericSponge<E, RATE, WIDTH>,
+    params: &P,
+    key: &[E::Fr],
+    nonce: &E::Fr,
+    associated_data: &[E::Fr],
+    message_len: usize)->Result<[E::Fr; RATE], CipherError>{
+
+    let mut header = key.to_vec();
+    header.push(*nonce);
+    header.push(u64_to_fe::<E>(associated_data.len() as u64));
+    header.push(u64_to_fe::<E>(message_len as u64));
+    header.extend_from_slice(associated_data);
+
+    duplex_native(sponge, params, &header)
+}
+
+// Absorbs the input, pads the last block and squeezes a full rate of output.
+fn duplex_native<
+    E: Engine,
+    P: HashParams<E, RATE, WIDTH>,
+    const RATE: usize,
+    const WIDTH: usize>(
+    sponge: &mut GenericSponge<E, RATE, WIDTH>,
+    params: &P,
+    input: &[E::Fr])->Result<[E::Fr; RATE], CipherError>{
+
+    if input.is_empty() {
+        return Err(CipherError::Sponge("empty duplex input".to_string()));
+    }
+    for el in input.iter() {
+        sponge.absorb(*el, params);
+    }
+    sponge.pad_if_necessary();
+
+    let mut output = [E::Fr::zero(); RATE];
+    for o in output.iter_mut() {
+        *o = sponge.squeeze(params).ok_or_else(|| CipherError::Sponge("no value squeezed".to_string()))?;
+    }
+    Ok(output)
+}
+
+fn absorb_header<
+    E: Engine,
+    CS: ConstraintSystem<E>,
+    P: HashParams<E, RATE, WIDTH>,
+    const RATE: usize,
+    const WIDTH: usize>(
+    cs: &mut CS,
+    sponge: &mut CircuitGenericSponge<E, RATE, WIDTH>,
+    params: &P,
+    key: &[Num<E>],
+    nonce: &Num<E>,
+    associated_data: &[Num<E>],
+    message_len: usize)->Result<[Num<E>; RATE], CipherError>{
+
+    let mut header = key.to_vec();
+    header.push(*nonce);
+    header.push(Num::Constant(u64_to_fe::<E>(associated_data.len() as u64)));
+    header.push(Num::Constant(u64_to_fe::<E>(message_len as u64)));
+    header.extend_from_slice(associated_data);
+
+    duplex(cs, sponge, params, &header)
+}
+
+fn duplex<
+    E: Engine,
+    CS: ConstraintSystem<E>,
+    P: HashParams<E, RATE, WIDTH>,
+    const RATE: usize,
+    const WIDTH: usize>(
+    cs: &mut CS,
+    sponge: &mut CircuitGenericSponge<E, RATE, WIDTH>,
+    params: &P,
+    input: &[Num<E>])->Result<[Num<E>; RATE], CipherError>{
+
+    if input.is_empty() {
+        return Err(CipherError::Sponge("empty duplex input".to_string()));
+    }
+    for el in input.iter() {
+        sponge.absorb(cs, *el, params)?;
+    }
+    sponge.pad_if_necessary();
+
+    let mut output = [Num::<E>::zero(); RATE];
+    for o in output.iter_mut() {
+        *o = sponge.squeeze_num(cs, params)?.ok_or_else(|| CipherError::Sponge("no value squeezed".to_string()))?;
+    }
+    Ok(output)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::tests::{init_cs, init_rng};
+    use franklin_crypto::bellman::pairing::bn256::{Bn256, Fr};
+    use rand::Rand;
+    use rescue_poseidon::RescueParams;
+
+    const RATE: usize = 2;
+    const WIDTH: usize = 3;
+
+    fn random_elements(rng: &mut rand::XorShiftRng, length: usize) -> Vec<Fr> {
+        (0..length).map(|_| Fr::rand(rng)).collect()
+    }
+
+    #[test]
+    fn test_aead_round_trip() {
+        let rng = &mut init_rng();
+        let params = RescueParams::<Bn256, RATE, WIDTH>::default();
+        let key = random_elements(rng, 2);
+        let nonce = Fr::rand(rng);
+
+        for (ad_length, message_length) in [(0, 1), (1, 2), (3, 5)].iter() {
+            let associated_data = random_elements(rng, *ad_length);
+            let message = random_elements(rng, *message_length);
+
+            let (ciphertext, tag) = aead_encryption_native(&params, &key, &nonce, &associated_data, &message).unwrap();
+            assert_eq!(ciphertext.len(), message.len());
+            let decrypted = aead_decryption_native(&params, &key, &nonce, &associated_data, &ciphertext, &tag).unwrap();
+            assert_eq!(decrypted, Some(message.clone()));
+
+            let mut tampered_ciphertext = ciphertext.clone();
+            tampered_ciphertext[0].add_assign(&Fr::one());
+            assert!(aead_decryption_native(&params, &key, &nonce, &associated_data, &tampered_ciphertext, &tag).unwrap().is_none());
+
+            let mut tampered_associated_data = associated_data.clone();
+            tampered_associated_data.push(Fr::one());
+            assert!(aead_decryption_native(&params, &key, &nonce, &tampered_associated_data, &ciphertext, &tag).unwrap().is_none());
+
+            let other_nonce = Fr::rand(rng);
+            assert!(aead_decryption_native(&params, &key, &other_nonce, &associated_data, &ciphertext, &tag).unwrap().is_none());
+        }
+    }
+
+    fn run_circuit_aead(tamper_tag: bool) -> bool {
+        let rng = &mut init_rng();
+        let cs = &mut init_cs::<Bn256>();
+        let params = RescueParams::<Bn256, RATE, WIDTH>::default();
+        let key = random_elements(rng, 2);
+        let nonce = Fr::rand(rng);
+        let associated_data = random_elements(rng, 3);
+        let message = random_elements(rng, 5);
+
+        let (expected_ciphertext, mut expected_tag) = aead_encryption_native(&params, &key, &nonce, &associated_data, &message).unwrap();
+
+        let key_as_nums: Vec<Num<Bn256>> = key.iter().map(|el| Num::alloc(cs, Some(*el)).unwrap()).collect();
+        let nonce_as_num = Num::alloc(cs, Some(nonce)).unwrap();
+        let associated_data_as_nums: Vec<Num<Bn256>> = associated_data.iter().map(|el| Num::alloc(cs, Some(*el)).unwrap()).collect();
+        let message_as_nums: Vec<Num<Bn256>> = message.iter().map(|el| Num::alloc(cs, Some(*el)).unwrap()).collect();
+
+        let (ciphertext, tag) = aead_encryption(cs, &params, &key_as_nums, &nonce_as_num, &associated_data_as_nums, &message_as_nums).unwrap();
+        for (actual, expected) in ciphertext.iter().zip(expected_ciphertext.iter()) {
+            assert_eq!(actual.get_value().unwrap(), *expected);
+        }
+        assert_eq!(tag.get_value().unwrap(), expected_tag);
+
+        if tamper_tag {
+            expected_tag.add_assign(&Fr::one());
+        }
+        let tag_as_num = Num::alloc(cs, Some(expected_tag)).unwrap();
+        let decrypted = aead_decryption(cs, &params, &key_as_nums, &nonce_as_num, &associated_data_as_nums, &ciphertext, &tag_as_num).unwrap();
+        for (actual, expected) in decrypted.iter().zip(message.iter()) {
+            assert_eq!(actual.get_value().unwrap(), *expected);
+        }
+
+        cs.finalize();
+        cs.is_satisfied()
+    }
+
+    #[test]
+    fn test_aead_native_matches_circuit() {
+        assert!(run_circuit_aead(false));
+    }
+
+    #[test]
+    fn test_aead_circuit_rejects_wrong_tag() {
+        assert!(!run_circuit_aead(true));
+    }
+}
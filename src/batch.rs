@@ -0,0 +1,98 @@
+use franklin_crypto::bellman::pairing::Engine;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use super::native::NativeKeySchedule;
+
+// Native cipher for encrypting many blocks under the same key schedule. The
+// schedule already holds the checked parameters and both matrices.
+pub struct PreparedNativeCipher<
+    'a,
+    E: Engine,
+    const SIZE: usize,
+    const RNUMBER: usize> {
+    key_schedule: &'a NativeKeySchedule<E, SIZE, RNUMBER>
+}
+
+impl<'a, E: Engine, const SIZE: usize, const RNUMBER: usize> PreparedNativeCipher<'a, E, SIZE, RNUMBER> {
+    pub fn new(key_schedule: &'a NativeKeySchedule<E, SIZE, RNUMBER>)->Self{
+        PreparedNativeCipher {
+            key_schedule
+        }
+    }
+
+    pub fn encrypt(&self, plaintext: &[E::Fr; SIZE]) -> [E::Fr; SIZE] {
+        self.key_schedule.encrypt(plaintext)
+    }
+
+    pub fn decrypt(&self, ciphertext: &[E::Fr; SIZE]) -> [E::Fr; SIZE] {
+        self.key_schedule.decrypt(ciphertext)
+    }
+
+    // Blocks are independent, so with the `parallel` feature they are spread
+    // over the rayon thread pool. The output keeps the order of the input.
+    pub fn encrypt_batch(&self, plaintexts: &[[E::Fr; SIZE]]) -> Vec<[E::Fr; SIZE]> {
+        #[cfg(feature = "parallel")]
+        let blocks = plaintexts.par_iter();
+        #[cfg(not(feature = "parallel"))]
+        let blocks = plaintexts.iter();
+
+        blocks.map(|block| self.encrypt(block)).collect()
+    }
+
+    pub fn decrypt_batch(&self, ciphertexts: &[[E::Fr; SIZE]]) -> Vec<[E::Fr; SIZE]> {
+        #[cfg(feature = "parallel")]
+        let blocks = ciphertexts.par_iter();
+        #[cfg(not(feature = "parallel"))]
+        let blocks = ciphertexts.iter();
+
+        blocks.map(|block| self.decrypt(block)).collect()
+    }
+}
+
+pub fn rescue_encryption_batch_native<
+    E: Engine,
+    const SIZE: usize,
+    const RNUMBER: usize>(
+    key_schedule: &NativeKeySchedule<E, SIZE, RNUMBER>,
+    plaintexts: &[[E::Fr; SIZE]])->Vec<[E::Fr; SIZE]>{
+
+    PreparedNativeCipher::new(key_schedule).encrypt_batch(plaintexts)
+}
+
+pub fn rescue_decryption_batch_native<
+    E: Engine,
+    const SIZE: usize,
+    const RNUMBER: usize>(
+    key_schedule: &NativeKeySchedule<E, SIZE, RNUMBER>,
+    ciphertexts: &[[E::Fr; SIZE]])->Vec<[E::Fr; SIZE]>{
+
+    PreparedNativeCipher::new(key_schedule).decrypt_batch(ciphertexts)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::cipher_tools::generate_cipher_params;
+    use crate::native::{rescue_encryption_with_key_schedule_native, rescue_decryption_with_key_schedule_native};
+    use crate::tests::{init_rng, test_inputs};
+    use franklin_crypto::bellman::pairing::bn256::Bn256;
+
+    #[test]
+    fn test_batch_matches_single_blocks() {
+        const SIZE: usize = 3;
+        const RNUMBER: usize = 7;
+        let rng = &mut init_rng();
+        let params = generate_cipher_params::<Bn256, _, SIZE, RNUMBER>(rng);
+        let key_schedule = NativeKeySchedule::new(&params, &test_inputs::<Bn256, SIZE>(rng)).unwrap();
+        let plaintexts: Vec<_> = (0..64).map(|_| test_inputs::<Bn256, SIZE>(rng)).collect();
+
+        let ciphertexts = rescue_encryption_batch_native(&key_schedule, &plaintexts);
+        assert_eq!(ciphertexts.len(), plaintexts.len());
+        for (plaintext, ciphertext) in plaintexts.iter().zip(ciphertexts.iter()) {
+            assert_eq!(*ciphertext, rescue_encryption_with_key_schedule_native(&key_schedule, plaintext));
+            assert_eq!(*plaintext, rescue_decryption_with_key_schedule_native(&key_schedule, ciphertext));
+        }
+        assert_eq!(rescue_decryption_batch_native(&key_schedule, &ciphertexts), plaintexts);
+        assert!(rescue_encryption_batch_native(&key_schedule, &[]).is_empty());
+    }
+}
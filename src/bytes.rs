@@ -0,0 +1,397 @@
+use franklin_crypto::plonk::circuit::allocated_num::Num;
+use franklin_crypto::plonk::circuit::boolean::{AllocatedBit, Boolean};
+use franklin_crypto::plonk::circuit::linear_combination::LinearCombination;
+use franklin_crypto::bellman::pairing::Engine;
+use franklin_crypto::bellman::pairing::ff::{Field, PrimeField, PrimeFieldRepr};
+use franklin_crypto::bellman::plonk::better_better_cs::cs::ConstraintSystem;
+use super::ctr::{ctr_encryption_native, ctr_decryption_native, ctr_encryption, ctr_decryption};
+use super::enc_dec::{ReadyCipherParams, KeySchedule};
+use super::native::NativeKeySchedule;
+use super::error::CipherError;
+
+// Bytes are packed little-endian into field elements, CAPACITY / 8 bytes per
+// element (31 for BN256), so every chunk is a canonical element. The message
+// is padded with a single 0x01 byte followed by zero bytes up to a multiple of
+// the chunk length. The padding always ends in the last element, which makes
+// the packing injective and lets the unpacking recover the exact length.
+pub fn bytes_per_element<E: Engine>() -> usize {
+    (E::Fr::CAPACITY / 8) as usize
+}
+
+// Number of elements the packing of a message of the given length takes.
+pub fn packed_length<E: Engine>(byte_length: usize) -> usize {
+    byte_length / bytes_per_element::<E>() + 1
+}
+
+pub fn pack_bytes_native<E: Engine>(bytes: &[u8]) -> Vec<E::Fr> {
+    let chunk_length = bytes_per_element::<E>();
+    let mut padded = bytes.to_vec();
+    padded.extend(padding::<E>(bytes.len()));
+
+    padded.chunks(chunk_length).map(|chunk| {
+        let mut buffer = vec![0u8; repr_length::<E>()];
+        buffer[..chunk_length].copy_from_slice(chunk);
+        let mut repr = <E::Fr as PrimeField>::Repr::default();
+        repr.read_le(&buffer[..]).expect("buffer has the length of a repr");
+        E::Fr::from_repr(repr).expect("chunk is below the modulus")
+    }).collect()
+}
+
+pub fn unpack_bytes_native<E: Engine>(elements: &[E::Fr]) -> Result<Vec<u8>, CipherError> {
+    let chunk_length = bytes_per_element::<E>();
+    let mut bytes = Vec::with_capacity(elements.len() * chunk_length);
+    for element in elements.iter() {
+        let mut buffer = vec![];
+        element.into_repr().write_le(&mut buffer)?;
+        if buffer[chunk_length..].iter().any(|byte| *byte != 0) {
+            return Err(CipherError::InvalidByteEncoding("element does not fit into a chunk".to_string()));
+        }
+        bytes.extend_from_slice(&buffer[..chunk_length]);
+    }
+
+    let padding_start = bytes.iter().rposition(|byte| *byte != 0)
+        .filter(|position| bytes[*position] == 1 && position + chunk_length >= bytes.len())
+        .ok_or_else(|| CipherError::InvalidByteEncoding("invalid padding".to_string()))?;
+    bytes.truncate(padding_start);
+    Ok(bytes)
+}
+
+// Packs bytes given as bits, eight bits per byte with the least significant
+// bit first. The bits are not range checked here, Boolean gadgets already are.
+pub fn pack_bytes<E: Engine, CS: ConstraintSystem<E>>(
+    cs: &mut CS,
+    bits: &[Boolean])->Result<Vec<Num<E>>, CipherError>{
+
+    if bits.len() % 8 != 0 {
+        return Err(CipherError::InvalidByteEncoding("number of bits is not a multiple of 8".to_string()));
+    }
+    let mut padded = bits.to_vec();
+    padded.extend(bytes_into_constant_bits(&padding::<E>(bits.len() / 8)));
+
+    let mut elements = Vec::with_capacity(packed_length::<E>(bits.len() / 8));
+    for chunk in padded.chunks(bytes_per_element::<E>() * 8) {
+        let mut lc = LinearCombination::zero();
+        let mut coeff = E::Fr::one();
+        for bit in chunk.iter() {
+            lc.add_assign_boolean_with_coeff(bit, coeff);
+            coeff.double();
+        }
+        elements.push(lc.into_num(cs)?);
+    }
+    Ok(elements)
+}
+
+// Decomposes the elements into the bits of a message of a known length. The
+// padding is enforced by adding it as constants to the decomposition, so only
+// the message bits are allocated.
+pub fn unpack_bytes<E: Engine, CS: ConstraintSystem<E>>(
+    cs: &mut CS,
+    elements: &[Num<E>],
+    byte_length: usize)->Result<Vec<Boolean>, CipherError>{
+
+    if elements.len() != packed_length::<E>(byte_length) {
+        return Err(CipherError::InvalidByteEncoding("number of elements does not match the length".to_string()));
+    }
+    let chunk_bits = bytes_per_element::<E>() * 8;
+    let padding_bits = bytes_into_constant_bits(&padding::<E>(byte_length));
+    let mut minus_one = E::Fr::one();
+    minus_one.negate();
+
+    let mut bits = Vec::with_capacity(byte_length * 8);
+    for (i, element) in elements.iter().enumerate() {
+        let repr = element.get_value().map(|value| value.into_repr());
+        let mut lc = LinearCombination::zero();
+        let mut coeff = E::Fr::one();
+        for j in 0..chunk_bits {
+            let position = i * chunk_bits + j;
+            let bit = if position < byte_length * 8 {
+                let value = repr.as_ref().map(|r| (r.as_ref()[j / 64] >> (j % 64)) & 1 == 1);
+                let bit = Boolean::from(AllocatedBit::alloc(cs, value)?);
+                bits.push(bit);
+                bit
+            } else {
+                padding_bits[position - byte_length * 8]
+            };
+            lc.add_assign_boolean_with_coeff(&bit, coeff);
+            coeff.double();
+        }
+        lc.add_assign_number_with_coeff(element, minus_one);
+        lc.enforce_zero(cs)?;
+    }
+    Ok(bits)
+}
+
+// Allocates every byte as eight bits, the least significant bit first.
+pub fn alloc_bytes<E: Engine, CS: ConstraintSystem<E>>(
+    cs: &mut CS,
+    bytes: &[Option<u8>])->Result<Vec<Boolean>, CipherError>{
+
+    let mut bits = Vec::with_capacity(bytes.len() * 8);
+    for byte in bytes.iter() {
+        for i in 0..8 {
+            let value = byte.map(|b| (b >> i) & 1 == 1);
+            bits.push(Boolean::from(AllocatedBit::alloc(cs, value)?));
+        }
+    }
+    Ok(bits)
+}
+
+// Decomposes a byte held in a field element into eight bits, the least
+// significant bit first. The bits are allocated as Boolean gadgets and their
+// weighted sum is enforced to equal the element, which range checks it to a
+// single byte.
+pub fn byte_into_bits<E: Engine, CS: ConstraintSystem<E>>(
+    cs: &mut CS,
+    byte: &Num<E>)->Result<[Boolean; 8], CipherError>{
+
+    let mut bits = [Boolean::constant(false); 8];
+    if let Num::Constant(value) = byte {
+        let repr = value.into_repr();
+        if repr.as_ref()[0] > 0xff || repr.as_ref()[1..].iter().any(|limb| *limb != 0) {
+            return Err(CipherError::InvalidByteEncoding("constant does not fit into a byte".to_string()));
+        }
+        for (i, bit) in bits.iter_mut().enumerate() {
+            *bit = Boolean::constant((repr.as_ref()[0] >> i) & 1 == 1);
+        }
+        return Ok(bits);
+    }
+
+    let low_limb = byte.get_value().map(|value| value.into_repr().as_ref()[0]);
+    let mut minus_one = E::Fr::one();
+    minus_one.negate();
+    let mut lc = LinearCombination::zero();
+    let mut coeff = E::Fr::one();
+    for (i, bit) in bits.iter_mut().enumerate() {
+        let value = low_limb.map(|limb| (limb >> i) & 1 == 1);
+        *bit = Boolean::from(AllocatedBit::alloc(cs, value)?);
+        lc.add_assign_boolean_with_coeff(bit, coeff);
+        coeff.double();
+    }
+    lc.add_assign_number_with_coeff(byte, minus_one);
+    lc.enforce_zero(cs)?;
+    Ok(bits)
+}
+
+// Same packing as `pack_bytes` for bytes that are already allocated as field
+// elements, every one of them is range checked by `byte_into_bits`.
+pub fn pack_byte_nums<E: Engine, CS: ConstraintSystem<E>>(
+    cs: &mut CS,
+    bytes: &[Num<E>])->Result<Vec<Num<E>>, CipherError>{
+
+    let mut bits = Vec::with_capacity(bytes.len() * 8);
+    for byte in bytes.iter() {
+        bits.extend_from_slice(&byte_into_bits(cs, byte)?);
+    }
+    pack_bytes(cs, &bits)
+}
+
+// Counter mode encryption of the packed message.
+pub fn encrypt_bytes_native<
+    E: Engine,
+    const SIZE: usize,
+    const RNUMBER: usize>(
+    key_schedule: &NativeKeySchedule<E, SIZE, RNUMBER>,
+    nonce: &E::Fr,
+    message: &[u8])->Result<Vec<E::Fr>, CipherError>{
+
+    ctr_encryption_native(key_schedule, nonce, &pack_bytes_native::<E>(message))
+}
+
+pub fn decrypt_bytes_native<
+    E: Engine,
+    const SIZE: usize,
+    const RNUMBER: usize>(
+    key_schedule: &NativeKeySchedule<E, SIZE, RNUMBER>,
+    nonce: &E::Fr,
+    ciphertext: &[E::Fr])->Result<Vec<u8>, CipherError>{
+
+    unpack_bytes_native::<E>(&ctr_decryption_native(key_schedule, nonce, ciphertext)?)
+}
+
+pub fn encrypt_bytes<
+    E: Engine,
+    CS: ConstraintSystem<E>,
+    const SIZE: usize,
+    const RNUMBER: usize>(
+    cs: &mut CS,
+    params: &ReadyCipherParams<E, SIZE, RNUMBER>,
+    key_schedule: &KeySchedule<E, SIZE, RNUMBER>,
+    nonce: &Num<E>,
+    message: &[Boolean])->Result<Vec<Num<E>>, CipherError>{
+
+    let packed = pack_bytes(cs, message)?;
+    ctr_encryption(cs, params, key_schedule, nonce, &packed)
+}
+
+pub fn decrypt_bytes<
+    E: Engine,
+    CS: ConstraintSystem<E>,
+    const SIZE: usize,
+    const RNUMBER: usize>(
+    cs: &mut CS,
+    params: &ReadyCipherParams<E, SIZE, RNUMBER>,
+    key_schedule: &KeySchedule<E, SIZE, RNUMBER>,
+    nonce: &Num<E>,
+    ciphertext: &[Num<E>],
+    byte_length: usize)->Result<Vec<Boolean>, CipherError>{
+
+    let packed = ctr_decryption(cs, params, key_schedule, nonce, ciphertext)?;
+    unpack_bytes(cs, &packed, byte_length)
+}
+
+fn padding<E: Engine>(byte_length: usize) -> Vec<u8> {
+    let chunk_length = bytes_per_element::<E>();
+    let mut padding = vec![0u8; chunk_length - byte_length % chunk_length];
+    padding[0] = 1;
+    padding
+}
+
+fn bytes_into_constant_bits(bytes: &[u8]) -> Vec<Boolean> {
+    bytes.iter().flat_map(|byte| (0..8).map(move |i| Boolean::constant((byte >> i) & 1 == 1))).collect()
+}
+
+fn repr_length<E: Engine>() -> usize {
+    <E::Fr as PrimeField>::Repr::default().as_ref().len() * 8
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::cipher_tools::generate_cipher_params;
+    use crate::enc_dec::construct_ready_params;
+    use crate::tests::{init_cs, init_rng};
+    use franklin_crypto::bellman::pairing::bn256::{Bn256, Fr};
+    use rand::{Rand, Rng};
+
+    const LENGTHS: [usize; 6] = [0, 1, 30, 31, 32, 62];
+
+    #[test]
+    fn test_bytes_round_trip() {
+        let rng = &mut init_rng();
+        assert_eq!(bytes_per_element::<Bn256>(), 31);
+
+        for length in LENGTHS.iter() {
+            let bytes: Vec<u8> = (0..*length).map(|_| rng.gen()).collect();
+            let packed = pack_bytes_native::<Bn256>(&bytes);
+            assert_eq!(packed.len(), packed_length::<Bn256>(*length));
+            assert_eq!(unpack_bytes_native::<Bn256>(&packed).unwrap(), bytes);
+        }
+
+        // Trailing zero bytes are part of the message, not of the padding.
+        assert_ne!(pack_bytes_native::<Bn256>(&[7]), pack_bytes_native::<Bn256>(&[7, 0]));
+    }
+
+    #[test]
+    fn test_invalid_packing_is_rejected() {
+        let mut packed = pack_bytes_native::<Bn256>(&[1, 2, 3]);
+        packed.push(Fr::zero());
+        assert!(matches!(unpack_bytes_native::<Bn256>(&packed), Err(CipherError::InvalidByteEncoding(_))));
+
+        assert!(matches!(unpack_bytes_native::<Bn256>(&[]), Err(CipherError::InvalidByteEncoding(_))));
+        assert!(matches!(unpack_bytes_native::<Bn256>(&[Fr::zero()]), Err(CipherError::InvalidByteEncoding(_))));
+
+        let mut too_large = Fr::zero();
+        too_large.sub_assign(&Fr::one());
+        assert!(matches!(unpack_bytes_native::<Bn256>(&[too_large]), Err(CipherError::InvalidByteEncoding(_))));
+    }
+
+    #[test]
+    fn test_bytes_native_matches_circuit() {
+        let rng = &mut init_rng();
+        let cs = &mut init_cs::<Bn256>();
+
+        for length in LENGTHS.iter() {
+            let bytes: Vec<u8> = (0..*length).map(|_| rng.gen()).collect();
+            let expected = pack_bytes_native::<Bn256>(&bytes);
+
+            let witness: Vec<Option<u8>> = bytes.iter().map(|byte| Some(*byte)).collect();
+            let bits = alloc_bytes(cs, &witness).unwrap();
+            let packed = pack_bytes(cs, &bits).unwrap();
+            assert_eq!(packed.len(), expected.len());
+            for (actual, expected) in packed.iter().zip(expected.iter()) {
+                assert_eq!(actual.get_value().unwrap(), *expected);
+            }
+
+            let unpacked = unpack_bytes(cs, &packed, *length).unwrap();
+            assert_eq!(unpacked.len(), bits.len());
+            for (actual, expected) in unpacked.iter().zip(bits.iter()) {
+                assert_eq!(actual.get_value().unwrap(), expected.get_value().unwrap());
+            }
+        }
+
+        cs.finalize();
+        assert!(cs.is_satisfied());
+    }
+
+    #[test]
+    fn test_byte_nums_are_range_checked() {
+        let rng = &mut init_rng();
+        let cs = &mut init_cs::<Bn256>();
+
+        let bytes: Vec<u8> = (0..40).map(|_| rng.gen()).collect();
+        let mut byte_nums = vec![Num::Constant(Fr::from_str("200").unwrap())];
+        for byte in bytes.iter() {
+            byte_nums.push(Num::alloc(cs, Some(Fr::from_str(&byte.to_string()).unwrap())).unwrap());
+        }
+        let mut message = vec![200u8];
+        message.extend_from_slice(&bytes);
+
+        let packed = pack_byte_nums(cs, &byte_nums).unwrap();
+        let expected = pack_bytes_native::<Bn256>(&message);
+        assert_eq!(packed.len(), expected.len());
+        for (actual, expected) in packed.iter().zip(expected.iter()) {
+            assert_eq!(actual.get_value().unwrap(), *expected);
+        }
+        cs.finalize();
+        assert!(cs.is_satisfied());
+
+        let out_of_range = Fr::from_str("256").unwrap();
+        assert!(matches!(byte_into_bits(&mut init_cs::<Bn256>(), &Num::Constant(out_of_range)), Err(CipherError::InvalidByteEncoding(_))));
+
+        let cs = &mut init_cs::<Bn256>();
+        let byte = Num::alloc(cs, Some(out_of_range)).unwrap();
+        byte_into_bits(cs, &byte).unwrap();
+        cs.finalize();
+        assert!(!cs.is_satisfied());
+    }
+
+    #[test]
+    fn test_encrypt_bytes_native_matches_circuit() {
+        const SIZE: usize = 3;
+        const RNUMBER: usize = 7;
+        let rng = &mut init_rng();
+        let cs = &mut init_cs::<Bn256>();
+        let params = generate_cipher_params::<Bn256, _, SIZE, RNUMBER>(rng);
+        let key = [Fr::rand(rng), Fr::rand(rng), Fr::rand(rng)];
+        let nonce = Fr::rand(rng);
+        let message: Vec<u8> = (0..100).map(|_| rng.gen()).collect();
+
+        let native_key_schedule = NativeKeySchedule::new(&params, &key).unwrap();
+        let expected = encrypt_bytes_native(&native_key_schedule, &nonce, &message).unwrap();
+        assert_eq!(decrypt_bytes_native(&native_key_schedule, &nonce, &expected).unwrap(), message);
+
+        let mut key_as_nums = [Num::<Bn256>::zero(); SIZE];
+        for i in 0..SIZE {
+            key_as_nums[i] = Num::alloc(cs, Some(key[i])).unwrap();
+        }
+        let nonce_as_num = Num::alloc(cs, Some(nonce)).unwrap();
+        let witness: Vec<Option<u8>> = message.iter().map(|byte| Some(*byte)).collect();
+        let message_as_bits = alloc_bytes(cs, &witness).unwrap();
+
+        let ready_params = construct_ready_params(&params).unwrap();
+        let key_schedule = KeySchedule::new(cs, &ready_params, &key_as_nums).unwrap();
+        let ciphertext = encrypt_bytes(cs, &ready_params, &key_schedule, &nonce_as_num, &message_as_bits).unwrap();
+        let decrypted = decrypt_bytes(cs, &ready_params, &key_schedule, &nonce_as_num, &ciphertext, message.len()).unwrap();
+
+        assert_eq!(ciphertext.len(), expected.len());
+        for (actual, expected) in ciphertext.iter().zip(expected.iter()) {
+            assert_eq!(actual.get_value().unwrap(), *expected);
+        }
+        for (actual, expected) in decrypted.iter().zip(message_as_bits.iter()) {
+            assert_eq!(actual.get_value().unwrap(), expected.get_value().unwrap());
+        }
+
+        cs.finalize();
+        assert!(cs.is_satisfied());
+    }
+}
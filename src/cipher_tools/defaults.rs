@@ -0,0 +1,72 @@
+use franklin_crypto::bellman::pairing::bn256::Bn256;
+use super::{CipherParams, generate_cipher_params_from_tag};
+use crate::error::CipherError;
+
+// Default parameter sets for BN256. Each set is derived from its tag by
+// `generate_cipher_params_from_tag`, so it can be regenerated independently
+// by every party. Round numbers give 128 bits of security for alpha = 5.
+pub const BN256_SIZE_2_TAG: &[u8] = b"Rescue_block_cipher_BN256_2_41";
+pub const BN256_SIZE_3_TAG: &[u8] = b"Rescue_block_cipher_BN256_3_29";
+pub const BN256_SIZE_4_TAG: &[u8] = b"Rescue_block_cipher_BN256_4_23";
+pub const BN256_SIZE_5_TAG: &[u8] = b"Rescue_block_cipher_BN256_5_19";
+
+pub type Bn256CipherParams2 = CipherParams<Bn256, 2, 41>;
+pub type Bn256CipherParams3 = CipherParams<Bn256, 3, 29>;
+pub type Bn256CipherParams4 = CipherParams<Bn256, 4, 23>;
+pub type Bn256CipherParams5 = CipherParams<Bn256, 5, 19>;
+
+pub fn bn256_cipher_params_2() -> Result<Bn256CipherParams2, CipherError> {
+    generate_cipher_params_from_tag(BN256_SIZE_2_TAG)
+}
+
+pub fn bn256_cipher_params_3() -> Result<Bn256CipherParams3, CipherError> {
+    generate_cipher_params_from_tag(BN256_SIZE_3_TAG)
+}
+
+pub fn bn256_cipher_params_4() -> Result<Bn256CipherParams4, CipherError> {
+    generate_cipher_params_from_tag(BN256_SIZE_4_TAG)
+}
+
+pub fn bn256_cipher_params_5() -> Result<Bn256CipherParams5, CipherError> {
+    generate_cipher_params_from_tag(BN256_SIZE_5_TAG)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::native::{rescue_decryption_native, rescue_encryption_native};
+    use crate::tests::{init_rng, test_inputs};
+
+    #[test]
+    fn test_params_from_tag_are_reproducible() {
+        let params = bn256_cipher_params_3().unwrap();
+        let same_params = bn256_cipher_params_3().unwrap();
+        assert_eq!(params.vect_for_matrix, same_params.vect_for_matrix);
+        assert_eq!(params.round_constants, same_params.round_constants);
+
+        let other_params: Bn256CipherParams3 = generate_cipher_params_from_tag(b"another tag").unwrap();
+        assert_ne!(params.vect_for_matrix, other_params.vect_for_matrix);
+        assert_ne!(params.round_constants, other_params.round_constants);
+    }
+
+    #[test]
+    fn test_default_params_are_secure() {
+        use crate::cipher_tools::rounds::DEFAULT_SECURITY_LEVEL;
+
+        assert!(bn256_cipher_params_2().unwrap().is_secure(DEFAULT_SECURITY_LEVEL));
+        assert!(bn256_cipher_params_3().unwrap().is_secure(DEFAULT_SECURITY_LEVEL));
+        assert!(bn256_cipher_params_4().unwrap().is_secure(DEFAULT_SECURITY_LEVEL));
+        assert!(bn256_cipher_params_5().unwrap().is_secure(DEFAULT_SECURITY_LEVEL));
+    }
+
+    #[test]
+    fn test_default_params_round_trip() {
+        let rng = &mut init_rng();
+        let params = bn256_cipher_params_4().unwrap();
+        let key = test_inputs::<Bn256, 4>(rng);
+        let plaintext = test_inputs::<Bn256, 4>(rng);
+
+        let ciphertext = rescue_encryption_native(&params, &key, &plaintext).unwrap();
+        assert_eq!(rescue_decryption_native(&params, &key, &ciphertext).unwrap(), plaintext);
+    }
+}
@@ -0,0 +1,144 @@
+use franklin_crypto::bellman::pairing::Engine;
+use franklin_crypto::bellman::pairing::ff::Field;
+use rescue_poseidon::HashParams;
+use rescue_poseidon::rescue::params::compute_round_constants;
+use rescue_poseidon::traits::{HashFamily, Sbox};
+use super::{CipherParams, is_secure_number_of_rounds};
+use super::mds::vectors_from_mds_matrix;
+use super::sboxes::{AlphaSBox, AlphaInverseSBox};
+use super::rounds::DEFAULT_SECURITY_LEVEL;
+use crate::error::CipherError;
+
+// Builds cipher parameters from the parameters of the Rescue hash, so the
+// hash and the cipher share one source of constants. The state is the full
+// hash state (SIZE = WIDTH) and the MDS matrix is the Cauchy matrix of the
+// hash. The rounds themselves stay those of this cipher.
+//
+// The 2 * full_rounds + 1 steps of the hash are chosen for an unkeyed
+// permutation and are fewer than the bound of `compute_number_of_rounds`.
+// The round constants are therefore the Blake2s stream of the hash continued
+// up to RNUMBER steps: the first ones are the constants of the hash, the
+// first of them whitening the key, and every further one is derived the same
+// way. Hash parameters whose constants are not from that stream are refused,
+// as are RNUMBER below the secure bound.
+pub fn cipher_params_from_hash_params<
+    E: Engine,
+    P: HashParams<E, RATE, WIDTH>,
+    const RATE: usize,
+    const WIDTH: usize,
+    const RNUMBER: usize>(
+    params: &P
+)-> Result<CipherParams<E, WIDTH, RNUMBER>, CipherError> {
+    if params.hash_family() != HashFamily::Rescue {
+        return Err(CipherError::InvalidHashParams(format!("{:?} is not Rescue", params.hash_family())));
+    }
+    let steps = 2 * params.number_of_full_rounds() + 1;
+    if steps > RNUMBER {
+        return Err(CipherError::InvalidNumberOfRounds(RNUMBER));
+    }
+
+    let alpha = match params.alpha() {
+        Sbox::Alpha(alpha) => *alpha,
+        Sbox::AlphaInverse(_) => return Err(CipherError::InvalidHashParams("alpha is an inverse S-box".to_string()))
+    };
+    if !is_secure_number_of_rounds::<E, WIDTH, RNUMBER>(DEFAULT_SECURITY_LEVEL, alpha) {
+        return Err(CipherError::InvalidNumberOfRounds(RNUMBER));
+    }
+    let sbox1 = AlphaSBox::<E, WIDTH>::new(alpha)?;
+    let sbox2 = AlphaInverseSBox::<E, WIDTH>::new(alpha)?;
+    if params.alpha_inv() != &Sbox::AlphaInverse(sbox2.alpha_inv) {
+        return Err(CipherError::InvalidHashParams("alpha^-1 does not invert alpha".to_string()));
+    }
+
+    let vect_for_matrix = vectors_from_mds_matrix::<E, WIDTH>(&params.mds_matrix())?;
+    let constants = compute_round_constants::<E, RATE, WIDTH>(RNUMBER);
+    if (0..steps).any(|i| params.constants_of_round(i) != constants[i]) {
+        return Err(CipherError::InvalidHashParams("round constants are not those of the Rescue hash".to_string()));
+    }
+    let mut round_constants = [[E::Fr::zero(); WIDTH]; RNUMBER];
+    round_constants.copy_from_slice(&constants);
+
+    let cipher_params = CipherParams {
+        vect_for_matrix,
+        sbox1,
+        sbox2,
+        round_constants
+    };
+    cipher_params.check()?;
+    Ok(cipher_params)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::cipher_tools::mds::construct_mds_matrix_native;
+    use crate::enc_dec::{construct_ready_params, rescue_encryption};
+    use crate::native::{rescue_encryption_native, rescue_decryption_native};
+    use crate::tests::{init_cs, init_rng, test_inputs};
+    use franklin_crypto::bellman::pairing::bn256::Bn256;
+    use franklin_crypto::plonk::circuit::allocated_num::Num;
+    use rescue_poseidon::{PoseidonParams, RescueParams};
+
+    // The default Rescue hash has 8 full rounds, i.e. 17 steps, while the
+    // cipher needs 29 for SIZE = 3.
+    const HASH_RNUMBER: usize = 17;
+    const RNUMBER: usize = 29;
+
+    #[test]
+    fn test_params_from_rescue_hash() {
+        let hash_params = RescueParams::<Bn256, 2, 3>::default();
+        let params = cipher_params_from_hash_params::<_, _, 2, 3, RNUMBER>(&hash_params).unwrap();
+        assert!(params.is_secure(DEFAULT_SECURITY_LEVEL));
+
+        assert_eq!(params.alpha(), 5);
+        assert_eq!(construct_mds_matrix_native::<Bn256, 3>(&params.vect_for_matrix).unwrap(), hash_params.mds_matrix());
+        for i in 0..HASH_RNUMBER {
+            assert_eq!(params.round_constants[i], hash_params.constants_of_round(i));
+        }
+        // the extension is deterministic
+        let again = cipher_params_from_hash_params::<_, _, 2, 3, RNUMBER>(&hash_params).unwrap();
+        assert_eq!(again.round_constants, params.round_constants);
+
+        let rng = &mut init_rng();
+        let cs = &mut init_cs::<Bn256>();
+        let key = test_inputs::<Bn256, 3>(rng);
+        let plaintext = test_inputs::<Bn256, 3>(rng);
+        let expected = rescue_encryption_native(&params, &key, &plaintext).unwrap();
+        assert_eq!(rescue_decryption_native(&params, &key, &expected).unwrap(), plaintext);
+
+        let mut key_as_nums = [Num::<Bn256>::zero(); 3];
+        let mut plaintext_as_nums = [Num::<Bn256>::zero(); 3];
+        for i in 0..3 {
+            key_as_nums[i] = Num::alloc(cs, Some(key[i])).unwrap();
+            plaintext_as_nums[i] = Num::alloc(cs, Some(plaintext[i])).unwrap();
+        }
+        let ready_params = construct_ready_params(&params).unwrap();
+        let ciphertext = rescue_encryption(cs, &ready_params, &key_as_nums, &plaintext_as_nums).unwrap();
+        for i in 0..3 {
+            assert_eq!(ciphertext[i].get_value().unwrap(), expected[i]);
+        }
+        cs.finalize();
+        assert!(cs.is_satisfied());
+    }
+
+    #[test]
+    fn test_unsuitable_hash_params_are_rejected() {
+        let hash_params = RescueParams::<Bn256, 2, 3>::default();
+        // fewer steps than the hash has
+        assert!(matches!(
+            cipher_params_from_hash_params::<_, _, 2, 3, 15>(&hash_params),
+            Err(CipherError::InvalidNumberOfRounds(15))
+        ));
+        // the rounds of the hash are too few for the keyed cipher
+        assert!(matches!(
+            cipher_params_from_hash_params::<_, _, 2, 3, HASH_RNUMBER>(&hash_params),
+            Err(CipherError::InvalidNumberOfRounds(HASH_RNUMBER))
+        ));
+
+        let poseidon_params = PoseidonParams::<Bn256, 2, 3>::default();
+        assert!(matches!(
+            cipher_params_from_hash_params::<_, _, 2, 3, RNUMBER>(&poseidon_params),
+            Err(CipherError::InvalidHashParams(_))
+        ));
+    }
+}
@@ -1,25 +1,32 @@
 use franklin_crypto::plonk::circuit::allocated_num::Num;
+use franklin_crypto::plonk::circuit::linear_combination::LinearCombination;
 use franklin_crypto::bellman::pairing::Engine;
+use franklin_crypto::bellman::pairing::ff::Field;
 use franklin_crypto::bellman::plonk::better_better_cs::cs::ConstraintSystem;
 use rand::Rng;
+use std::convert::TryInto;
+use crate::error::CipherError;
 
 pub struct MdsMatrix<E: Engine, const SIZE: usize> {
-	data: [[Num<E>; SIZE]; SIZE]
+	data: [[E::Fr; SIZE]; SIZE]
 }
 
 impl<E: Engine, const SIZE: usize> MdsMatrix<E,SIZE>{
 	pub fn zero_matrix()-> Self {
-		let data = [[Num::<E>::zero(); SIZE]; SIZE];
+		let data = [[E::Fr::zero(); SIZE]; SIZE];
 		MdsMatrix {
 			data
 		}
 	}
 
-    pub fn get_row(&self, n: usize) -> [Num<E>; SIZE] {
-        if n >= SIZE {
-            panic!();
-        }
-        self.data[n]
+	pub fn from_native(matrix: &[[E::Fr; SIZE]; SIZE])-> Self {
+		MdsMatrix {
+			data: *matrix
+		}
+	}
+
+    pub fn get_row(&self, n: usize) -> Result<[E::Fr; SIZE], CipherError> {
+        self.data.get(n).copied().ok_or(CipherError::RowOutOfRange(n))
     }
 }
 
@@ -40,6 +47,23 @@ pub fn generate_vectors_for_matrix<
     }
 }
 
+// Checks that the vectors define a Cauchy matrix: both have SIZE elements
+// and all 2*SIZE elements are distinct.
+pub fn check_vectors_for_matrix<
+    E: Engine,
+    const SIZE: usize
+>(
+    vectors: &[Vec<E::Fr>; 2]
+)-> Result<(), CipherError> {
+    if vectors[0].len() != SIZE || vectors[1].len() != SIZE {
+        return Err(CipherError::InvalidMdsVectors);
+    }
+    if !okey_vectors::<E, SIZE>(&vectors[0], &vectors[1]) {
+        return Err(CipherError::InvalidMdsVectors);
+    }
+    Ok(())
+}
+
 fn okey_vectors<E: Engine, const SIZE: usize>(x: & Vec<E::Fr>, y: & Vec<E::Fr>)->bool {
     
     for i in 0..(SIZE as usize) {
@@ -72,161 +96,364 @@ fn okey_vectors<E: Engine, const SIZE: usize>(x: & Vec<E::Fr>, y: & Vec<E::Fr>)-
     return true;
 }
 
-pub fn construct_mds_matrix<
-    E: Engine, 
-    CS: ConstraintSystem<E>,
+// Recovers vectors x, y with matrix[i][j] = 1 / (x[i] - y[j]) from a Cauchy
+// matrix. The vectors are only defined up to a common shift, so y[0] is
+// fixed to zero. Fails if the matrix is not a Cauchy matrix.
+pub fn vectors_from_mds_matrix<
+    E: Engine,
     const SIZE: usize
 >(
-    cs: &mut CS,
-    vectors: &mut [Vec<E::Fr>; 2]
-)-> MdsMatrix<E, SIZE> {
-    let mut mds_matrix = MdsMatrix::<E,SIZE>::zero_matrix();
+    matrix: &[[E::Fr; SIZE]; SIZE]
+)-> Result<[Vec<E::Fr>; 2], CipherError> {
+    if SIZE == 0 {
+        return Err(CipherError::InvalidMdsVectors);
+    }
+    let mut differences = [[E::Fr::zero(); SIZE]; SIZE];
+    for i in 0..SIZE {
+        for j in 0..SIZE {
+            differences[i][j] = matrix[i][j].inverse().ok_or(CipherError::InvalidMdsVectors)?;
+        }
+    }
 
-	let x: Vec<Num<E>> = vectors[0].iter_mut().map(|number| Num::alloc(cs, Some(*number)).unwrap()).collect();
-	let y: Vec<Num<E>> = vectors[1].iter_mut().map(|number| Num::alloc(cs, Some(*number)).unwrap()).collect();
+    let x: Vec<E::Fr> = (0..SIZE).map(|i| differences[i][0]).collect();
+    let y: Vec<E::Fr> = (0..SIZE).map(|j| {
+        let mut y = x[0];
+        y.sub_assign(&differences[0][j]);
+        y
+    }).collect();
 
-    if !veryfy_vector_corectnes::<E, CS, SIZE>(cs, &x, &y){
-        panic!();
+    for i in 0..SIZE {
+        for j in 0..SIZE {
+            let mut difference = x[i];
+            difference.sub_assign(&y[j]);
+            if difference != differences[i][j] {
+                return Err(CipherError::InvalidMdsVectors);
+            }
+        }
     }
 
-    for (i, x) in x.into_iter().enumerate() {
-        for (j, y) in y.iter().enumerate() {
-            mds_matrix.data[i][j] = x.sub(cs,y).unwrap().inverse(cs).unwrap();
+    let vectors = [x, y];
+    check_vectors_for_matrix::<E, SIZE>(&vectors)?;
+    Ok(vectors)
+}
+
+pub fn construct_mds_matrix<
+    E: Engine,
+    const SIZE: usize
+>(
+    vectors: &[Vec<E::Fr>; 2]
+)-> Result<MdsMatrix<E, SIZE>, CipherError> {
+    let mds_matrix = construct_mds_matrix_native::<E, SIZE>(vectors)?;
+    Ok(MdsMatrix::from_native(&mds_matrix))
+}
+
+pub fn construct_mds_matrix_native<
+    E: Engine,
+    const SIZE: usize
+>(
+    vectors: &[Vec<E::Fr>; 2]
+)-> Result<[[E::Fr; SIZE]; SIZE], CipherError> {
+    check_vectors_for_matrix::<E, SIZE>(vectors)?;
+
+    let mut mds_matrix = [[E::Fr::zero(); SIZE]; SIZE];
+    for (i, x) in vectors[0].iter().enumerate() {
+        for (j, y) in vectors[1].iter().enumerate() {
+            let mut element = *x;
+            element.sub_assign(y);
+            mds_matrix[i][j] = element.inverse().ok_or(CipherError::InvalidMdsVectors)?;
         }
     }
-    mds_matrix
+    Ok(mds_matrix)
 }
 
-fn veryfy_vector_corectnes<
-    E: Engine, 
-    CS: ConstraintSystem<E>,
+pub fn construct_inverse_matrix<
+    E: Engine,
     const SIZE: usize
->(cs: &mut CS, x: & Vec<Num<E>>, y: & Vec<Num<E>>) -> bool{
-    for i in 0..(SIZE as usize) {
-        let el = x[i];
-        for other in x[(i+1)..].iter() {
-            if Num::equals(cs, &el, &other).unwrap().get_value().unwrap() {
-                return false;
-            }
+>(
+    vectors: &[Vec<E::Fr>; 2]
+)-> Result<MdsMatrix<E, SIZE>, CipherError> {
+    let mds_matrix = construct_mds_matrix_native::<E, SIZE>(vectors)?;
+    let mds_invert_matrix = construct_inverse_matrix_native::<E, SIZE>(&mds_matrix)?;
+    Ok(MdsMatrix::from_native(&mds_invert_matrix))
+}
+
+// Inverts the matrix natively by Gauss-Jordan elimination.
+pub fn construct_inverse_matrix_native<
+    E: Engine,
+    const SIZE: usize
+>(
+    mds_matrix: &[[E::Fr; SIZE]; SIZE]
+)-> Result<[[E::Fr; SIZE]; SIZE], CipherError> {
+    let mut matrix = *mds_matrix;
+    let mut mds_invert_matrix = identity_matrix_native::<E, SIZE>();
+
+    for col in 0..SIZE {
+        let pivot = (col..SIZE)
+            .find(|&row| !matrix[row][col].is_zero())
+            .ok_or(CipherError::SingularMatrix)?;
+        matrix.swap(col, pivot);
+        mds_invert_matrix.swap(col, pivot);
+
+        let pivot_inv = matrix[col][col].inverse().ok_or(CipherError::SingularMatrix)?;
+        for j in 0..SIZE {
+            matrix[col][j].mul_assign(&pivot_inv);
+            mds_invert_matrix[col][j].mul_assign(&pivot_inv);
         }
-    }
 
-    for i in 0..(SIZE as usize) {
-        let el = y[i];
-        for other in y[(i+1)..].iter() {
-            if Num::equals(cs, &el, &other).unwrap().get_value().unwrap() {
-                return false;
+        for row in 0..SIZE {
+            if row == col || matrix[row][col].is_zero() {
+                continue;
             }
-        }
-    }
+            let factor = matrix[row][col];
+            for j in 0..SIZE {
+                let mut a = matrix[col][j];
+                a.mul_assign(&factor);
+                matrix[row][j].sub_assign(&a);
 
-    for i in 0..(SIZE as usize) {
-        let el = x[i];
-        for other in y.iter() {
-            if Num::equals(cs, &el, &other).unwrap().get_value().unwrap() {
-                return false;
+                let mut b = mds_invert_matrix[col][j];
+                b.mul_assign(&factor);
+                mds_invert_matrix[row][j].sub_assign(&b);
             }
         }
     }
-    return true;
+
+    Ok(mds_invert_matrix)
 }
 
-fn compute_determinant<
-    E: Engine, 
-    CS: ConstraintSystem<E>, 
+pub fn identity_matrix_native<
+    E: Engine,
+    const SIZE: usize
+>()-> [[E::Fr; SIZE]; SIZE] {
+    let mut identity = [[E::Fr::zero(); SIZE]; SIZE];
+    for i in 0..SIZE {
+        identity[i][i] = E::Fr::one();
+    }
+    identity
+}
+
+pub fn matrix_product_native<
+    E: Engine,
     const SIZE: usize
 >(
-    cs: &mut CS,
-    mds_matrix: &MdsMatrix<E, SIZE>    
-)->Option<Num<E>> {
-    if SIZE != 3 {
-        return None;
+    x: &[[E::Fr; SIZE]; SIZE],
+    y: &[[E::Fr; SIZE]; SIZE]
+)-> [[E::Fr; SIZE]; SIZE] {
+    let mut res = [[E::Fr::zero(); SIZE]; SIZE];
+    for i in 0..SIZE {
+        for j in 0..SIZE {
+            for k in 0..SIZE {
+                let mut z = x[i][k];
+                z.mul_assign(&y[k][j]);
+                res[i][j].add_assign(&z);
+            }
+        }
     }
-    let s123 = mds_matrix.data[0][0].mul(cs, &mds_matrix.data[1][1]).unwrap().mul(cs, &mds_matrix.data[2][2]).unwrap();
-    let s132 = mds_matrix.data[0][0].mul(cs, &mds_matrix.data[1][2]).unwrap().mul(cs, &mds_matrix.data[2][1]).unwrap();
-    let s213 = mds_matrix.data[0][1].mul(cs, &mds_matrix.data[1][0]).unwrap().mul(cs, &mds_matrix.data[2][2]).unwrap();
-    let s231 = mds_matrix.data[0][1].mul(cs, &mds_matrix.data[1][2]).unwrap().mul(cs, &mds_matrix.data[2][0]).unwrap();
-    let s312 = mds_matrix.data[0][2].mul(cs, &mds_matrix.data[1][0]).unwrap().mul(cs, &mds_matrix.data[2][1]).unwrap();
-    let s321 = mds_matrix.data[0][2].mul(cs, &mds_matrix.data[1][1]).unwrap().mul(cs, &mds_matrix.data[2][0]).unwrap();
+    res
+}
 
-    let result = s123.add(cs, &s231).unwrap()
-        .add(cs, &s312).unwrap()
-        .sub(cs, &s132).unwrap()
-        .sub(cs, &s321).unwrap()
-        .sub(cs, &s213).unwrap();
+// Computes matrix vector product and assigns result into same vector.
+// The matrix is public, so the product is a linear combination with
+// constant coefficients and costs no gates.
+pub fn matrix_vector_product<
+    E: Engine,
+    const SIZE: usize>(
+    matrix: &MdsMatrix<E, SIZE>,
+    vector: &mut [LinearCombination<E>; SIZE]){
+    let vec_cloned = vector.clone();
 
-    Some(result)
+    for (idx, row) in matrix.data.iter().enumerate() {
+        vector[idx] = LinearCombination::zero();
+        for (factor, lc) in row.iter().zip(vec_cloned.iter()) {
+            vector[idx].add_assign_scaled(lc, *factor);
+        }
+    }
 }
 
-pub fn construct_inverse_matrix<
-    E: Engine, 
-    CS: ConstraintSystem<E>, 
-    const SIZE: usize    
->(
-    cs: &mut CS,
-    mds_matrix: &MdsMatrix<E, SIZE>  
-)-> Option<MdsMatrix<E, SIZE>> {
-    if SIZE != 3 {
-        return None;
+pub fn add_vectors<
+    E: Engine,
+    const SIZE: usize>(
+    x: &mut [LinearCombination<E>; SIZE],
+    y: &[Num<E>; SIZE]){
+    for i in 0..SIZE {
+        x[i].add_assign_number_with_coeff(&y[i], E::Fr::one());
     }
-    let det = compute_determinant(cs, mds_matrix).unwrap();
-    if det.is_zero(cs).unwrap().get_value().unwrap() {
-        return None;
+}
+
+pub fn sub_vectors<
+    E: Engine,
+    const SIZE: usize>(
+    x: &mut [LinearCombination<E>; SIZE],
+    y: &[Num<E>; SIZE]){
+    let mut minus_one = E::Fr::one();
+    minus_one.negate();
+    for i in 0..SIZE {
+        x[i].add_assign_number_with_coeff(&y[i], minus_one);
     }
+}
 
-    let mut mds_invert_matrix = MdsMatrix::<E,SIZE>::zero_matrix();
+pub fn nums_into_lcs<
+    E: Engine,
+    const SIZE: usize>(
+    x: &[Num<E>; SIZE])->[LinearCombination<E>; SIZE]{
+    x.iter()
+        .map(|el| LinearCombination::from(*el))
+        .collect::<Vec<LinearCombination<E>>>()
+        .try_into()
+        .expect("constant array of LCs")
+}
 
-    for i in 0..(SIZE as usize) {
-        for j in 0..(SIZE as usize) {
-            let a = mds_matrix.data[(i+1)%3][(j+1)%3].mul(cs, &mds_matrix.data[(i+2)%3][(j+2)%3]).unwrap();
-            let b = mds_matrix.data[(i+1)%3][(j+2)%3].mul(cs, &mds_matrix.data[(i+2)%3][(j+1)%3]).unwrap();
-            let c = a.sub(cs, &b).unwrap();
-            mds_invert_matrix.data[j][i] = c.div(cs, &det).unwrap();
-        }
+pub fn lcs_into_nums<
+    E: Engine,
+    CS: ConstraintSystem<E>,
+    const SIZE: usize>(
+    cs: &mut CS,
+    x: [LinearCombination<E>; SIZE])->Result<[Num<E>; SIZE], CipherError>{
+    let mut res = [Num::<E>::zero(); SIZE];
+    for (r, lc) in res.iter_mut().zip(IntoIterator::into_iter(x)) {
+        *r = lc.into_num(cs)?;
     }
-
-    Some(mds_invert_matrix)
+    Ok(res)
 }
 
-pub fn dot_product<
-    E: Engine, 
-    CS: ConstraintSystem<E>, 
+pub fn matrix_vector_product_native<
+    E: Engine,
     const SIZE: usize>(
-    cs: &mut CS, 
-    x: &[Num<E>; SIZE], 
-    y: &[Num<E>; SIZE])->Num<E>{
-    let mut res = Num::<E>::zero();
+    matrix: &[[E::Fr; SIZE]; SIZE],
+    x: &[E::Fr; SIZE])->[E::Fr; SIZE]{
+    let mut res = [E::Fr::zero(); SIZE];
     for i in 0..SIZE {
-        let z = x[i].mul(cs, &y[i]).unwrap();
-        res = res.add(cs, &z).unwrap();
+        for j in 0..SIZE {
+            let mut z = matrix[i][j];
+            z.mul_assign(&x[j]);
+            res[i].add_assign(&z);
+        }
     }
     res
 }
 
-pub fn add_vectors<
-    E: Engine, 
-    CS: ConstraintSystem<E>, 
+pub fn add_vectors_native<
+    E: Engine,
     const SIZE: usize>(
-    cs: &mut CS, 
-    x: &[Num<E>; SIZE], 
-    y: &[Num<E>; SIZE])->[Num<E>;SIZE]{
-    let mut res = [Num::<E>::zero(); SIZE];
+    x: &[E::Fr; SIZE],
+    y: &[E::Fr; SIZE])->[E::Fr; SIZE]{
+    let mut res = *x;
     for i in 0..SIZE {
-        res[i] = x[i].add(cs, &y[i]).unwrap();
+        res[i].add_assign(&y[i]);
     }
     res
 }
 
-pub fn sub_vectors<
-    E: Engine, 
-    CS: ConstraintSystem<E>, 
+pub fn sub_vectors_native<
+    E: Engine,
     const SIZE: usize>(
-    cs: &mut CS, 
-    x: &[Num<E>; SIZE], 
-    y: &[Num<E>; SIZE])->[Num<E>;SIZE]{
-    let mut res = [Num::<E>::zero(); SIZE];
+    x: &[E::Fr; SIZE],
+    y: &[E::Fr; SIZE])->[E::Fr; SIZE]{
+    let mut res = *x;
     for i in 0..SIZE {
-        res[i] = x[i].sub(cs, &y[i]).unwrap();
+        res[i].sub_assign(&y[i]);
     }
     res
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::tests::{init_cs, init_rng, test_inputs};
+    use franklin_crypto::bellman::pairing::bn256::Bn256;
+
+    fn test_inverse_matrix<E: Engine, const SIZE: usize>() {
+        let rng = &mut init_rng();
+        let vectors = generate_vectors_for_matrix::<E, _, SIZE>(rng);
+        let mds_matrix = construct_mds_matrix_native::<E, SIZE>(&vectors).unwrap();
+        let mds_invert_matrix = construct_inverse_matrix_native::<E, SIZE>(&mds_matrix).expect("inverse");
+
+        let identity = identity_matrix_native::<E, SIZE>();
+        assert_eq!(matrix_product_native::<E, SIZE>(&mds_matrix, &mds_invert_matrix), identity);
+        assert_eq!(matrix_product_native::<E, SIZE>(&mds_invert_matrix, &mds_matrix), identity);
+    }
+
+    #[test]
+    fn test_mds_inverse_matrix() {
+        test_inverse_matrix::<Bn256, 2>();
+        test_inverse_matrix::<Bn256, 3>();
+        test_inverse_matrix::<Bn256, 4>();
+        test_inverse_matrix::<Bn256, 5>();
+        test_inverse_matrix::<Bn256, 8>();
+        test_inverse_matrix::<Bn256, 12>();
+    }
+
+    #[test]
+    fn test_matrix_vector_product() {
+        const SIZE: usize = 4;
+        let rng = &mut init_rng();
+        let cs = &mut init_cs::<Bn256>();
+
+        let vectors = generate_vectors_for_matrix::<Bn256, _, SIZE>(rng);
+        let mds_matrix = construct_mds_matrix_native::<Bn256, SIZE>(&vectors).unwrap();
+        let input = test_inputs::<Bn256, SIZE>(rng);
+        let expected = matrix_vector_product_native::<Bn256, SIZE>(&mds_matrix, &input);
+
+        let mut input_as_nums = [Num::<Bn256>::zero(); SIZE];
+        for i in 0..SIZE {
+            input_as_nums[i] = Num::alloc(cs, Some(input[i])).unwrap();
+        }
+        let start = cs.n();
+        let mut vector = nums_into_lcs(&input_as_nums);
+        matrix_vector_product(&MdsMatrix::from_native(&mds_matrix), &mut vector);
+        let actual = lcs_into_nums(cs, vector).unwrap();
+
+        // only collapsing the linear combinations into variables costs gates
+        assert!(cs.n() - start <= SIZE * 2);
+        for i in 0..SIZE {
+            assert_eq!(actual[i].get_value().unwrap(), expected[i]);
+        }
+    }
+
+    #[test]
+    fn test_singular_matrix_has_no_inverse() {
+        let mut matrix = identity_matrix_native::<Bn256, 4>();
+        matrix[3] = matrix[1];
+        assert!(matches!(
+            construct_inverse_matrix_native::<Bn256, 4>(&matrix),
+            Err(CipherError::SingularMatrix)
+        ));
+    }
+
+    #[test]
+    fn test_invalid_vectors_are_rejected() {
+        let rng = &mut init_rng();
+        let mut vectors = generate_vectors_for_matrix::<Bn256, _, 3>(rng);
+        assert!(construct_mds_matrix_native::<Bn256, 3>(&vectors).is_ok());
+        assert!(matches!(
+            construct_mds_matrix_native::<Bn256, 4>(&vectors),
+            Err(CipherError::InvalidMdsVectors)
+        ));
+
+        vectors[1][2] = vectors[0][1];
+        assert!(matches!(
+            construct_mds_matrix_native::<Bn256, 3>(&vectors),
+            Err(CipherError::InvalidMdsVectors)
+        ));
+    }
+
+    #[test]
+    fn test_vectors_from_mds_matrix() {
+        let rng = &mut init_rng();
+        let vectors = generate_vectors_for_matrix::<Bn256, _, 4>(rng);
+        let mds_matrix = construct_mds_matrix_native::<Bn256, 4>(&vectors).unwrap();
+
+        let recovered = vectors_from_mds_matrix::<Bn256, 4>(&mds_matrix).unwrap();
+        assert_eq!(construct_mds_matrix_native::<Bn256, 4>(&recovered).unwrap(), mds_matrix);
+
+        let mut not_cauchy = mds_matrix;
+        not_cauchy[2][3] = not_cauchy[3][2];
+        assert!(matches!(
+            vectors_from_mds_matrix::<Bn256, 4>(&not_cauchy),
+            Err(CipherError::InvalidMdsVectors)
+        ));
+        assert!(matches!(
+            vectors_from_mds_matrix::<Bn256, 4>(&identity_matrix_native::<Bn256, 4>()),
+            Err(CipherError::InvalidMdsVectors)
+        ));
+    }
+}
@@ -1,45 +1,200 @@
 pub mod mds;
 pub mod sboxes;
+pub mod defaults;
+pub mod rounds;
+pub mod serialization;
+pub mod hash_params;
 
-use rand::Rng;
+use rand::{Rng, SeedableRng, chacha::ChaChaRng};
 use franklin_crypto::bellman::pairing::Engine;
-use franklin_crypto::bellman::pairing::ff::Field;
-use std::marker::PhantomData;
-use mds::generate_vectors_for_matrix;
-use sboxes::{QuinticSBox, QuinticInverseSBox};
+use franklin_crypto::bellman::pairing::ff::{Field, PrimeField, PrimeFieldRepr};
+use franklin_crypto::constants;
+use franklin_crypto::group_hash::{BlakeHasher, GroupHasher};
+use mds::{generate_vectors_for_matrix, check_vectors_for_matrix};
+use sboxes::{AlphaSBox, AlphaInverseSBox, compute_alpha, compute_inverse_alpha, is_valid_alpha};
+use rounds::compute_number_of_rounds;
+use crate::error::CipherError;
 
 pub struct CipherParams<
     E: Engine,
     const SIZE: usize,
     const RNUMBER: usize> {
     pub vect_for_matrix: [Vec<E::Fr>; 2],
-    pub sbox1: QuinticSBox<E, SIZE>,
-    pub sbox2: QuinticInverseSBox<E, SIZE>,
+    pub sbox1: AlphaSBox<E, SIZE>,
+    pub sbox2: AlphaInverseSBox<E, SIZE>,
     pub round_constants: [[E::Fr; SIZE]; RNUMBER]
 }
 
+impl<E: Engine, const SIZE: usize, const RNUMBER: usize> CipherParams<E, SIZE, RNUMBER> {
+    pub fn alpha(&self) -> u64 {
+        self.sbox1.alpha
+    }
+
+    pub fn is_secure(&self, security_level: usize) -> bool {
+        is_secure_number_of_rounds::<E, SIZE, RNUMBER>(security_level, self.alpha())
+    }
+
+    // Parameters may come from outside (e.g. a parameter file), so they are
+    // checked before any matrix is built from them.
+    pub fn check(&self) -> Result<(), CipherError> {
+        if SIZE == 0 {
+            return Err(CipherError::UnsupportedSize(SIZE));
+        }
+        if RNUMBER == 0 {
+            return Err(CipherError::InvalidNumberOfRounds(RNUMBER));
+        }
+        let alpha = self.alpha();
+        if !is_valid_alpha::<E>(alpha) || self.sbox2.alpha != alpha {
+            return Err(CipherError::InvalidAlpha(alpha));
+        }
+        if self.sbox2.alpha_inv != compute_inverse_alpha::<E, 4>(alpha) {
+            return Err(CipherError::InvalidAlpha(alpha));
+        }
+        check_vectors_for_matrix::<E, SIZE>(&self.vect_for_matrix)
+    }
+}
+
+fn is_secure_number_of_rounds<
+    E: Engine,
+    const SIZE: usize,
+    const RNUMBER: usize>(security_level: usize, alpha: u64)-> bool {
+    compute_number_of_rounds::<E>(SIZE, security_level, alpha as usize).map_or(false, |rounds| RNUMBER >= rounds)
+}
+
+// Same as `generate_cipher_params` but refuses RNUMBER below the bound
+// of `compute_number_of_rounds` for the given security level.
+pub fn generate_secure_cipher_params<
+    E: Engine,
+    R: Rng,
+    const SIZE: usize,
+    const RNUMBER: usize>(rng: &mut R, security_level: usize)-> Option<CipherParams<E, SIZE, RNUMBER>> {
+    if !is_secure_number_of_rounds::<E, SIZE, RNUMBER>(security_level, compute_alpha::<E>()) {
+        return None;
+    }
+    Some(generate_cipher_params(rng))
+}
+
+pub fn generate_secure_cipher_params_from_tag<
+    E: Engine,
+    const SIZE: usize,
+    const RNUMBER: usize>(tag: &[u8], security_level: usize)-> Result<Option<CipherParams<E, SIZE, RNUMBER>>, CipherError> {
+    if !is_secure_number_of_rounds::<E, SIZE, RNUMBER>(security_level, compute_alpha::<E>()) {
+        return Ok(None);
+    }
+    Ok(Some(generate_cipher_params_from_tag(tag)?))
+}
+
 pub fn generate_cipher_params<
     E: Engine, 
     R: Rng, 
     const SIZE: usize,
     const RNUMBER: usize>(rng: &mut R)-> CipherParams<E, SIZE, RNUMBER> {
+    generate_cipher_params_with_alpha(rng, compute_alpha::<E>()).expect("computed alpha is valid")
+}
+
+// The smallest valid alpha is the cheapest one in the circuit; larger ones
+// need fewer rounds, which `is_secure` takes into account.
+pub fn generate_cipher_params_with_alpha<
+    E: Engine, 
+    R: Rng, 
+    const SIZE: usize,
+    const RNUMBER: usize>(rng: &mut R, alpha: u64)-> Result<CipherParams<E, SIZE, RNUMBER>, CipherError> {
     
+    let sbox1 = AlphaSBox::<E, SIZE>::new(alpha)?;
+    let sbox2 = AlphaInverseSBox::<E, SIZE>::new(alpha)?;
     let vect_for_matrix = generate_vectors_for_matrix::<E, R, SIZE>(rng);
-
-    let sbox1 = QuinticSBox::<E, SIZE>{
-        _marker: PhantomData::<E>::default()
-    };
-    let sbox2 = QuinticInverseSBox::<E, SIZE>{
-        _marker: PhantomData::<E>::default()
-    };
     let round_constants = generate_round_constants::<E, R, SIZE, RNUMBER>(rng);
 
-    CipherParams{
+    Ok(CipherParams{
         vect_for_matrix,
         sbox1,
         sbox2,
         round_constants
+    })
+}
+
+// Nothing-up-my-sleeve parameters: the round constants are Blake2s outputs
+// of the tag and a counter, the MDS vectors are sampled from a ChaCha
+// stream seeded by the Blake2s digest of the tag. Anyone who knows the tag
+// regenerates exactly the same cipher.
+pub fn generate_cipher_params_from_tag<
+    E: Engine,
+    const SIZE: usize,
+    const RNUMBER: usize>(tag: &[u8])-> Result<CipherParams<E, SIZE, RNUMBER>, CipherError> {
+
+    let rng = &mut init_rng_from_tag(tag);
+    let vect_for_matrix = generate_vectors_for_matrix::<E, ChaChaRng, SIZE>(rng);
+
+    let sbox1 = AlphaSBox::<E, SIZE>::default();
+    let sbox2 = AlphaInverseSBox::<E, SIZE>::default();
+    let round_constants = compute_round_constants::<E, SIZE, RNUMBER>(tag)?;
+
+    Ok(CipherParams{
+        vect_for_matrix,
+        sbox1,
+        sbox2,
+        round_constants
+    })
+}
+
+const ROUND_CONSTANTS_PERSONALIZATION: &[u8; 8] = b"ResBC_rc";
+const MDS_PERSONALIZATION: &[u8; 8] = b"ResBC_md";
+
+fn compute_round_constants<
+    E: Engine,
+    const SIZE: usize,
+    const RNUMBER: usize
+>(
+    tag: &[u8]
+)-> Result<[[E::Fr; SIZE]; RNUMBER], CipherError> {
+    let mut roconst = [[E::Fr::zero(); SIZE]; RNUMBER];
+    let mut nonce = 0u32;
+
+    for i in 0..RNUMBER {
+        for j in 0..SIZE {
+            loop {
+                let mut h = BlakeHasher::new(&ROUND_CONSTANTS_PERSONALIZATION[..]);
+                h.update(constants::GH_FIRST_BLOCK);
+                h.update(tag);
+                h.update(&nonce.to_be_bytes());
+                let h = h.finalize();
+                assert!(h.len() == 32);
+                nonce += 1;
+
+                let mut constant_repr = <E::Fr as PrimeField>::Repr::default();
+                constant_repr.read_le(&h[..])?;
+
+                if let Ok(constant) = E::Fr::from_repr(constant_repr) {
+                    if !constant.is_zero() {
+                        roconst[i][j] = constant;
+                        break;
+                    }
+                }
+            }
+        }
+    }
+    Ok(roconst)
+}
+
+fn init_rng_from_tag(tag: &[u8]) -> ChaChaRng {
+    let mut h = BlakeHasher::new(&MDS_PERSONALIZATION[..]);
+    h.update(constants::GH_FIRST_BLOCK);
+    h.update(tag);
+    let h = h.finalize();
+    assert!(h.len() == 32);
+
+    let mut seed = [0u32; 8];
+    for (i, chunk) in h.chunks_exact(4).enumerate() {
+        seed[i] = u32::from_be_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
     }
+
+    ChaChaRng::from_seed(&seed)
+}
+
+pub(crate) fn u64_to_fe<E: Engine>(value: u64) -> E::Fr {
+    let mut repr = <E::Fr as PrimeField>::Repr::default();
+    repr.as_mut()[0] = value;
+    E::Fr::from_repr(repr).expect("u64 fits into the field")
 }
 
 fn generate_round_constants<
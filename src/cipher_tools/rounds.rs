@@ -0,0 +1,86 @@
+use franklin_crypto::bellman::pairing::Engine;
+use franklin_crypto::bellman::pairing::ff::PrimeField;
+use num_bigint::BigUint;
+use num_traits::{One, Zero};
+
+pub const DEFAULT_SECURITY_LEVEL: usize = 128;
+
+// Computes the smallest RNUMBER for which the cipher reaches the given
+// security level. The bound follows Rescue-Prime: the number of double
+// rounds (one alpha and one alpha^-1 S-box layer) is 1.5 times the larger
+// of the statistical bound l0, the Groebner basis bound l1 and 5. Each
+// double round takes two subkeys and the whitening key takes one more.
+// Returns None for an empty state, an alpha below 3 or a security level the
+// bound can not reach.
+pub fn compute_number_of_rounds<E: Engine>(size: usize, security_level: usize, alpha: usize) -> Option<usize> {
+    let double_rounds = get_number_of_rounds(size, security_level, alpha, E::Fr::NUM_BITS as usize)?;
+    Some(2 * double_rounds + 1)
+}
+
+// Follows the private `get_number_of_rounds` of rescue_prime in
+// rescue-poseidon, which can not be reused as is: it is written for the
+// sponge, so only the rate part of the state is known to the attacker, it
+// leaves out the statistical bound l0, and it computes 2^security_level in a
+// u128, which overflows at the 128 bit default used here.
+fn get_number_of_rounds(m: usize, security_level: usize, alpha: usize, field_bits: usize) -> Option<usize> {
+    if m == 0 || alpha < 3 {
+        return None;
+    }
+
+    fn factorial(n: &BigUint) -> BigUint {
+        if n.is_zero() {
+            return BigUint::one();
+        } else {
+            return n * factorial(&(n - &BigUint::one()));
+        }
+    }
+
+    fn binomial(n: &BigUint, k: &BigUint) -> BigUint {
+        factorial(n) / (factorial(&(n - k)) * factorial(k))
+    }
+
+    // statistical attacks
+    let log_alpha_minus_one = ((alpha - 1) as f64).log2();
+    let l0 = (2.0 * security_level as f64 / ((m + 1) as f64 * (field_bits as f64 - log_alpha_minus_one))).ceil() as usize;
+
+    // Groebner basis attacks, every element of the state is known to the attacker
+    let dcon = |n: usize| -> BigUint {
+        let tmp = ((alpha - 1) * m * (n - 1)) as f64;
+        BigUint::from((0.5 * tmp).floor() as u64) + BigUint::from(2u8)
+    };
+
+    let v = |n: usize| -> BigUint {
+        BigUint::from(m * (n - 1) + m)
+    };
+
+    let target = BigUint::one() << security_level;
+
+    let l1 = (1..100).find(|n| binomial(&(v(*n) + dcon(*n)), &v(*n)).pow(2u32) > target)?;
+
+    Some((1.5 * l0.max(l1).max(5) as f64).ceil() as usize)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use franklin_crypto::bellman::pairing::bn256::Bn256;
+
+    #[test]
+    fn test_number_of_rounds() {
+        assert_eq!(compute_number_of_rounds::<Bn256>(2, DEFAULT_SECURITY_LEVEL, 5), Some(41));
+        assert_eq!(compute_number_of_rounds::<Bn256>(3, DEFAULT_SECURITY_LEVEL, 5), Some(29));
+        assert_eq!(compute_number_of_rounds::<Bn256>(4, DEFAULT_SECURITY_LEVEL, 5), Some(23));
+        assert_eq!(compute_number_of_rounds::<Bn256>(5, DEFAULT_SECURITY_LEVEL, 5), Some(19));
+        // the margin keeps at least 5 double rounds for wide states
+        assert_eq!(compute_number_of_rounds::<Bn256>(12, DEFAULT_SECURITY_LEVEL, 5), Some(17));
+        assert!(compute_number_of_rounds::<Bn256>(3, 80, 5) < compute_number_of_rounds::<Bn256>(3, 128, 5));
+    }
+
+    #[test]
+    fn test_number_of_rounds_rejects_invalid_inputs() {
+        assert_eq!(compute_number_of_rounds::<Bn256>(0, DEFAULT_SECURITY_LEVEL, 5), None);
+        assert_eq!(compute_number_of_rounds::<Bn256>(3, DEFAULT_SECURITY_LEVEL, 2), None);
+        assert_eq!(compute_number_of_rounds::<Bn256>(3, 100_000, 5), None);
+        assert!(!crate::cipher_tools::is_secure_number_of_rounds::<Bn256, 0, 7>(DEFAULT_SECURITY_LEVEL, 5));
+    }
+}
@@ -1,64 +1,217 @@
-use franklin_crypto::plonk::circuit::allocated_num::Num;
-use franklin_crypto::bellman::Engine;
-use franklin_crypto::bellman::plonk::better_better_cs::cs::ConstraintSystem;
+use franklin_crypto::plonk::circuit::allocated_num::{AllocatedNum, Num};
+use franklin_crypto::bellman::{Engine, Field, PrimeField, SynthesisError};
+use franklin_crypto::bellman::plonk::better_better_cs::cs::{
+    ArithmeticTerm, ConstraintSystem, MainGateTerm, PlonkConstraintSystemParams
+};
 use std::marker::PhantomData;
+use crate::error::CipherError;
 
-pub struct QuinticSBox<E: Engine, const SIZE: usize>{
+// Smallest alpha >= 3 for which x^alpha is a permutation of the field,
+// i.e. gcd(alpha, p - 1) = 1. This is 5 for BN256.
+pub fn compute_alpha<E: Engine>() -> u64 {
+    (3..).find(|alpha| is_valid_alpha::<E>(*alpha)).expect("p - 1 has finitely many divisors")
+}
+
+pub fn is_valid_alpha<E: Engine>(alpha: u64) -> bool {
+    alpha >= 3 && gcd(alpha, modulus_minus_one_rem::<E>(alpha)) == 1
+}
+
+#[derive(Clone)]
+pub struct AlphaSBox<E: Engine, const SIZE: usize>{
+    pub alpha: u64,
     pub _marker: PhantomData<E>
 }
 
-impl<E: Engine, const SIZE: usize> QuinticSBox<E, SIZE> {
-    pub fn apply<CS: ConstraintSystem<E>>(&self, cs: &mut CS, elements: &mut [Num<E>; SIZE]) {
+impl<E: Engine, const SIZE: usize> AlphaSBox<E, SIZE> {
+    pub fn new(alpha: u64) -> Result<Self, CipherError> {
+        if !is_valid_alpha::<E>(alpha) {
+            return Err(CipherError::InvalidAlpha(alpha));
+        }
+        Ok(AlphaSBox {
+            alpha,
+            _marker: PhantomData::<E>::default()
+        })
+    }
+
+    pub fn apply<CS: ConstraintSystem<E>>(&self, cs: &mut CS, elements: &mut [Num<E>; SIZE]) -> Result<(), CipherError> {
+        for element in elements.iter_mut() {
+            let value = match *element {
+                Num::Constant(value) => {
+                    *element = Num::Constant(value.pow(&[self.alpha]));
+                    continue;
+                }
+                Num::Variable(value) => value
+            };
+            let result = apply_alpha_power(cs, &value, self.alpha, None)?;
+            *element = Num::Variable(result);
+        }
+        Ok(())
+    }
+
+    pub fn apply_native(&self, elements: &mut [E::Fr; SIZE]) {
         for element in elements.iter_mut() {
-            let mut result = element.mul(cs, element).unwrap();
-            result = result.mul(cs, element).unwrap();
-            result = result.mul(cs, element).unwrap();
-            result = result.mul(cs, element).unwrap();
-            *element = result;
+            *element = element.pow(&[self.alpha]);
         }
     }
 }
 
-pub struct QuinticInverseSBox<E: Engine, const SIZE: usize>{
+impl<E: Engine, const SIZE: usize> Default for AlphaSBox<E, SIZE> {
+    fn default() -> Self {
+        Self::new(compute_alpha::<E>()).expect("computed alpha is valid")
+    }
+}
+
+#[derive(Clone)]
+pub struct AlphaInverseSBox<E: Engine, const SIZE: usize>{
+    pub alpha: u64,
+    pub alpha_inv: [u64; 4],
     pub _marker: PhantomData<E>
 }
 
-impl<E: Engine, const SIZE: usize> QuinticInverseSBox<E, SIZE> {
-    pub fn apply<CS: ConstraintSystem<E>>(&self, cs: &mut CS, elements: &mut [Num<E>; SIZE]) {
-        let alpha = 5;
-        let alpha_inv = compute_inverse_alpha::<E, 4>(alpha);
-        for element in elements.iter_mut() {
-            let old_elem = (*element).clone();
-            big_pow(cs, element, &alpha_inv);
+impl<E: Engine, const SIZE: usize> AlphaInverseSBox<E, SIZE> {
+    pub fn new(alpha: u64) -> Result<Self, CipherError> {
+        if !is_valid_alpha::<E>(alpha) {
+            return Err(CipherError::InvalidAlpha(alpha));
+        }
+        Ok(AlphaInverseSBox {
+            alpha,
+            alpha_inv: compute_inverse_alpha::<E, 4>(alpha),
+            _marker: PhantomData::<E>::default()
+        })
+    }
 
-            let mut elem = element.mul(cs, &element).unwrap();
-            elem = elem.mul(cs, &element).unwrap();
-            elem = elem.mul(cs, &element).unwrap();
-            elem = elem.mul(cs, &element).unwrap();
+    // The root x^(1/alpha) is allocated as a witness, so the circuit only
+    // has to check that its alpha-th power equals the original element.
+    pub fn apply<CS: ConstraintSystem<E>>(&self, cs: &mut CS, elements: &mut [Num<E>; SIZE]) -> Result<(), CipherError> {
+        for element in elements.iter_mut() {
+            let value = match *element {
+                Num::Constant(value) => {
+                    *element = Num::Constant(value.pow(&self.alpha_inv));
+                    continue;
+                }
+                Num::Variable(value) => value
+            };
+            let powered = AllocatedNum::alloc(cs, || {
+                let base = value.get_value().ok_or(SynthesisError::AssignmentMissing)?;
+                Ok(base.pow(&self.alpha_inv))
+            })?;
+            let _ = apply_alpha_power(cs, &powered, self.alpha, Some(value))?;
+            *element = Num::Variable(powered);
+        }
+        Ok(())
+    }
 
-            old_elem.enforce_equal(cs, &elem).unwrap();
+    pub fn apply_native(&self, elements: &mut [E::Fr; SIZE]) {
+        for element in elements.iter_mut() {
+            *element = element.pow(&self.alpha_inv);
         }
     }
 }
 
-fn compute_inverse_alpha<E: Engine, const N: usize>(alpha: u64) -> [u64; N] {
+impl<E: Engine, const SIZE: usize> Default for AlphaInverseSBox<E, SIZE> {
+    fn default() -> Self {
+        Self::new(compute_alpha::<E>()).expect("computed alpha is valid")
+    }
+}
+
+pub(crate) fn compute_inverse_alpha<E: Engine, const N: usize>(alpha: u64) -> [u64; N] {
     rescue_poseidon::common::utils::compute_gcd::<E, N>(alpha).expect("inverse of alpha")
 }
 
-fn big_pow<E: Engine, CS: ConstraintSystem<E>, const N: usize>(cs: &mut CS, num: &mut Num<E>, power: &[u64; N]){
-    let mut res = Num::<E>::one();
-    let mut pow = *power;
-    for n in 0..N {
-        for _ in 0..(64 as usize) {
-            if pow[n]%2 == 0 {
-                *num = num.mul(cs, &num).unwrap();
-                pow[n] = pow[n]/2;
-            } else {
-                res = res.mul(cs, &num).unwrap();
-                *num = num.mul(cs, &num).unwrap();
-                pow[n] = (pow[n]-1)/2;
+// (p - 1) mod n, folding the little-endian limbs of p from the top.
+fn modulus_minus_one_rem<E: Engine>(n: u64) -> u64 {
+    let n = n as u128;
+    let mut rem = 0u128;
+    for limb in E::Fr::char().as_ref().iter().rev() {
+        rem = ((rem << 64) | *limb as u128) % n;
+    }
+    ((rem + n - 1) % n) as u64
+}
+
+fn gcd(mut a: u64, mut b: u64) -> u64 {
+    while b != 0 {
+        let rem = a % b;
+        a = b;
+        b = rem;
+    }
+    a
+}
+
+// Computes value^alpha by square-and-multiply or, if `existing` is given,
+// enforces that it is value^alpha. Every step is a product of two factors,
+// so the last one doubles as the check and costs no additional gate. Alpha
+// equal to 5 goes through the dedicated gadget below.
+fn apply_alpha_power<E: Engine, CS: ConstraintSystem<E>>(
+    cs: &mut CS,
+    value: &AllocatedNum<E>,
+    alpha: u64,
+    existing: Option<AllocatedNum<E>>,
+) -> Result<AllocatedNum<E>, SynthesisError> {
+    if alpha == 5 {
+        return apply_5th_power(cs, value, existing);
+    }
+
+    let mut multiplications = vec![];
+    for i in (0..(63 - alpha.leading_zeros())).rev() {
+        multiplications.push(false);
+        if (alpha >> i) & 1 == 1 {
+            multiplications.push(true);
+        }
+    }
+
+    let mut result = *value;
+    for (i, multiply) in multiplications.iter().enumerate() {
+        let factor = if *multiply { *value } else { result };
+        result = match existing {
+            Some(power) if i + 1 == multiplications.len() => {
+                enforce_product(cs, &result, &factor, &power)?;
+                power
             }
+            _ => result.mul(cs, &factor)?
+        };
+    }
+    Ok(result)
+}
+
+fn enforce_product<E: Engine, CS: ConstraintSystem<E>>(
+    cs: &mut CS,
+    a: &AllocatedNum<E>,
+    b: &AllocatedNum<E>,
+    product: &AllocatedNum<E>,
+) -> Result<(), SynthesisError> {
+    let mut term = MainGateTerm::<E>::new();
+    let product_term = ArithmeticTerm::from_variable(a.get_variable())
+        .mul_by_variable(b.get_variable());
+    term.add_assign(product_term);
+    term.sub_assign(ArithmeticTerm::from_variable(product.get_variable()));
+    cs.allocate_main_gate(term)?;
+    Ok(())
+}
+
+// Computes the 5th power of an allocated element or, if `existing_5th` is given,
+// enforces that it is the 5th power. If constraint system has support of
+// custom gate then computation costs only single gate.
+fn apply_5th_power<E: Engine, CS: ConstraintSystem<E>>(
+    cs: &mut CS,
+    value: &AllocatedNum<E>,
+    existing_5th: Option<AllocatedNum<E>>,
+) -> Result<AllocatedNum<E>, SynthesisError> {
+    if CS::Params::HAS_CUSTOM_GATES && CS::Params::STATE_WIDTH >= 4 {
+        return franklin_crypto::plonk::circuit::custom_rescue_gate::apply_5th_power(
+            cs,
+            value,
+            existing_5th,
+        );
+    }
+
+    let squared = value.square(cs)?;
+    let quad = squared.square(cs)?;
+
+    match existing_5th {
+        Some(fifth) => {
+            enforce_product(cs, &quad, value, &fifth)?;
+            Ok(fifth)
         }
+        None => quad.mul(cs, value),
     }
-    *num = res;
 }
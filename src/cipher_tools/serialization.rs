@@ -0,0 +1,415 @@
+use franklin_crypto::bellman::pairing::Engine;
+use franklin_crypto::bellman::pairing::ff::{Field, PrimeField, PrimeFieldRepr};
+use serde::{Deserialize, Serialize};
+use std::io::{Read, Write};
+use super::CipherParams;
+use super::sboxes::{AlphaSBox, AlphaInverseSBox};
+use crate::error::CipherError;
+
+// Binary layout of the parameters, all integers are big-endian:
+//
+//   magic "RBCP" | version: u8 |
+//   element length: u16 | scalar field modulus |
+//   base field length: u16 | base field modulus |
+//   SIZE: u32 | RNUMBER: u32 | alpha: u64 |
+//   2 * SIZE elements of the MDS vectors | RNUMBER * SIZE round constants
+//
+// Field elements are big-endian integers of the element length. The two
+// moduli identify the field and the engine the parameters belong to.
+const MAGIC: &[u8; 4] = b"RBCP";
+pub const PARAMS_FORMAT_VERSION: u8 = 1;
+
+// Human-readable form of the same data, field elements are hex strings
+// of their big-endian bytes.
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CipherParamsJson {
+    version: u8,
+    field_modulus: String,
+    base_field_modulus: String,
+    size: usize,
+    rnumber: usize,
+    alpha: u64,
+    mds_vectors: Vec<Vec<String>>,
+    round_constants: Vec<Vec<String>>
+}
+
+// Only the dimensions of an encoded parameter set, enough to pick the
+// matching CipherParams<E, SIZE, RNUMBER> before decoding it.
+#[derive(Deserialize)]
+struct DimensionsJson {
+    size: usize,
+    rnumber: usize
+}
+
+pub fn is_json_encoding(bytes: &[u8]) -> bool {
+    bytes.iter().find(|byte| !byte.is_ascii_whitespace()) == Some(&b'{')
+}
+
+// SIZE and RNUMBER of parameters written by `write` or `to_json`. Nothing
+// else is checked, `read` and `from_json` still validate the whole encoding.
+pub fn params_dimensions(bytes: &[u8]) -> Result<(usize, usize), CipherError> {
+    if is_json_encoding(bytes) {
+        let decoded: DimensionsJson = serde_json::from_slice(bytes)
+            .map_err(|err| CipherError::InvalidEncoding(err.to_string()))?;
+        return Ok((decoded.size, decoded.rnumber));
+    }
+
+    let mut reader = bytes;
+    let mut magic = [0u8; 4];
+    reader.read_exact(&mut magic)?;
+    if magic != *MAGIC {
+        return Err(CipherError::InvalidEncoding("not a cipher parameters file".to_string()));
+    }
+    let _version = read_u8(&mut reader)?;
+    for _ in 0..2 {
+        let length = read_u16(&mut reader)? as usize;
+        let mut modulus = vec![0u8; length];
+        reader.read_exact(&mut modulus)?;
+    }
+    let size = read_u32(&mut reader)? as usize;
+    let rnumber = read_u32(&mut reader)? as usize;
+    Ok((size, rnumber))
+}
+
+impl<E: Engine, const SIZE: usize, const RNUMBER: usize> CipherParams<E, SIZE, RNUMBER> {
+    pub fn write<W: Write>(&self, mut writer: W) -> Result<(), CipherError> {
+        writer.write_all(MAGIC)?;
+        writer.write_all(&[PARAMS_FORMAT_VERSION])?;
+
+        let field_modulus = modulus_bytes::<E::Fr>();
+        writer.write_all(&(field_modulus.len() as u16).to_be_bytes())?;
+        writer.write_all(&field_modulus)?;
+        let base_field_modulus = modulus_bytes::<E::Fq>();
+        writer.write_all(&(base_field_modulus.len() as u16).to_be_bytes())?;
+        writer.write_all(&base_field_modulus)?;
+
+        writer.write_all(&(SIZE as u32).to_be_bytes())?;
+        writer.write_all(&(RNUMBER as u32).to_be_bytes())?;
+        writer.write_all(&self.alpha().to_be_bytes())?;
+
+        for element in self.vect_for_matrix.iter().flatten() {
+            element.into_repr().write_be(&mut writer)?;
+        }
+        for element in self.round_constants.iter().flatten() {
+            element.into_repr().write_be(&mut writer)?;
+        }
+        Ok(())
+    }
+
+    pub fn read<R: Read>(mut reader: R) -> Result<Self, CipherError> {
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic)?;
+        if magic != *MAGIC {
+            return Err(CipherError::InvalidEncoding("not a cipher parameters file".to_string()));
+        }
+        let version = read_u8(&mut reader)?;
+
+        let field_length = read_u16(&mut reader)? as usize;
+        let mut field_modulus = vec![0u8; field_length];
+        reader.read_exact(&mut field_modulus)?;
+        let base_field_length = read_u16(&mut reader)? as usize;
+        let mut base_field_modulus = vec![0u8; base_field_length];
+        reader.read_exact(&mut base_field_modulus)?;
+
+        let size = read_u32(&mut reader)? as usize;
+        let rnumber = read_u32(&mut reader)? as usize;
+        let alpha = read_u64(&mut reader)?;
+        check_header::<E, SIZE, RNUMBER>(version, &field_modulus, &base_field_modulus, size, rnumber)?;
+
+        let mut vect_for_matrix = [Vec::with_capacity(SIZE), Vec::with_capacity(SIZE)];
+        for vector in vect_for_matrix.iter_mut() {
+            for _ in 0..SIZE {
+                vector.push(read_element::<E, _>(&mut reader)?);
+            }
+        }
+        let mut round_constants = [[E::Fr::zero(); SIZE]; RNUMBER];
+        for element in round_constants.iter_mut().flatten() {
+            *element = read_element::<E, _>(&mut reader)?;
+        }
+
+        params_from_parts(alpha, vect_for_matrix, round_constants)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![];
+        self.write(&mut bytes).expect("writing into a vector never fails");
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, CipherError> {
+        let mut reader = bytes;
+        let params = Self::read(&mut reader)?;
+        if !reader.is_empty() {
+            return Err(CipherError::InvalidEncoding("trailing bytes after parameters".to_string()));
+        }
+        Ok(params)
+    }
+
+    pub fn to_json(&self) -> String {
+        let encoded = CipherParamsJson {
+            version: PARAMS_FORMAT_VERSION,
+            field_modulus: hex::encode(modulus_bytes::<E::Fr>()),
+            base_field_modulus: hex::encode(modulus_bytes::<E::Fq>()),
+            size: SIZE,
+            rnumber: RNUMBER,
+            alpha: self.alpha(),
+            mds_vectors: self.vect_for_matrix.iter()
+                .map(|vector| vector.iter().map(element_to_hex::<E>).collect())
+                .collect(),
+            round_constants: self.round_constants.iter()
+                .map(|constants| constants.iter().map(element_to_hex::<E>).collect())
+                .collect()
+        };
+        serde_json::to_string_pretty(&encoded).expect("parameters are serializable")
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, CipherError> {
+        let decoded: CipherParamsJson = serde_json::from_str(json)
+            .map_err(|err| CipherError::InvalidEncoding(err.to_string()))?;
+
+        let field_modulus = decode_hex(&decoded.field_modulus)?;
+        let base_field_modulus = decode_hex(&decoded.base_field_modulus)?;
+        check_header::<E, SIZE, RNUMBER>(
+            decoded.version, &field_modulus, &base_field_modulus, decoded.size, decoded.rnumber
+        )?;
+
+        if decoded.mds_vectors.len() != 2 || decoded.round_constants.len() != RNUMBER {
+            return Err(CipherError::InvalidEncoding("dimensions do not match SIZE and RNUMBER".to_string()));
+        }
+        let mut vect_for_matrix = [vec![], vec![]];
+        for (vector, encoded) in vect_for_matrix.iter_mut().zip(decoded.mds_vectors.iter()) {
+            if encoded.len() != SIZE {
+                return Err(CipherError::InvalidEncoding("dimensions do not match SIZE and RNUMBER".to_string()));
+            }
+            for element in encoded.iter() {
+                vector.push(element_from_hex::<E>(element)?);
+            }
+        }
+        let mut round_constants = [[E::Fr::zero(); SIZE]; RNUMBER];
+        for (constants, encoded) in round_constants.iter_mut().zip(decoded.round_constants.iter()) {
+            if encoded.len() != SIZE {
+                return Err(CipherError::InvalidEncoding("dimensions do not match SIZE and RNUMBER".to_string()));
+            }
+            for (constant, element) in constants.iter_mut().zip(encoded.iter()) {
+                *constant = element_from_hex::<E>(element)?;
+            }
+        }
+
+        params_from_parts(decoded.alpha, vect_for_matrix, round_constants)
+    }
+}
+
+fn check_header<E: Engine, const SIZE: usize, const RNUMBER: usize>(
+    version: u8,
+    field_modulus: &[u8],
+    base_field_modulus: &[u8],
+    size: usize,
+    rnumber: usize
+) -> Result<(), CipherError> {
+    if version != PARAMS_FORMAT_VERSION {
+        return Err(CipherError::InvalidEncoding(format!("unsupported format version {}", version)));
+    }
+    if field_modulus != &modulus_bytes::<E::Fr>()[..] || base_field_modulus != &modulus_bytes::<E::Fq>()[..] {
+        return Err(CipherError::InvalidEncoding("parameters belong to another engine".to_string()));
+    }
+    if size != SIZE {
+        return Err(CipherError::UnsupportedSize(size));
+    }
+    if rnumber != RNUMBER {
+        return Err(CipherError::InvalidNumberOfRounds(rnumber));
+    }
+    Ok(())
+}
+
+fn params_from_parts<E: Engine, const SIZE: usize, const RNUMBER: usize>(
+    alpha: u64,
+    vect_for_matrix: [Vec<E::Fr>; 2],
+    round_constants: [[E::Fr; SIZE]; RNUMBER]
+) -> Result<CipherParams<E, SIZE, RNUMBER>, CipherError> {
+    if round_constants.iter().flatten().any(|constant| constant.is_zero()) {
+        return Err(CipherError::InvalidEncoding("round constants must be nonzero".to_string()));
+    }
+
+    let params = CipherParams {
+        vect_for_matrix,
+        sbox1: AlphaSBox::<E, SIZE>::new(alpha)?,
+        sbox2: AlphaInverseSBox::<E, SIZE>::new(alpha)?,
+        round_constants
+    };
+    params.check()?;
+    Ok(params)
+}
+
+fn modulus_bytes<F: PrimeField>() -> Vec<u8> {
+    let mut bytes = vec![];
+    F::char().write_be(&mut bytes).expect("writing into a vector never fails");
+    bytes
+}
+
+fn read_element<E: Engine, R: Read>(reader: &mut R) -> Result<E::Fr, CipherError> {
+    let mut repr = <E::Fr as PrimeField>::Repr::default();
+    repr.read_be(reader)?;
+    E::Fr::from_repr(repr).map_err(|_| CipherError::InvalidEncoding("field element is not canonical".to_string()))
+}
+
+fn element_to_hex<E: Engine>(element: &E::Fr) -> String {
+    let mut bytes = vec![];
+    element.into_repr().write_be(&mut bytes).expect("writing into a vector never fails");
+    hex::encode(bytes)
+}
+
+fn element_from_hex<E: Engine>(encoded: &str) -> Result<E::Fr, CipherError> {
+    let bytes = decode_hex(encoded)?;
+    let mut reader = &bytes[..];
+    let element = read_element::<E, _>(&mut reader)
+        .map_err(|_| CipherError::InvalidEncoding(format!("invalid field element {}", encoded)))?;
+    if !reader.is_empty() {
+        return Err(CipherError::InvalidEncoding(format!("invalid field element {}", encoded)));
+    }
+    Ok(element)
+}
+
+fn decode_hex(encoded: &str) -> Result<Vec<u8>, CipherError> {
+    hex::decode(encoded).map_err(|err| CipherError::InvalidEncoding(err.to_string()))
+}
+
+fn read_u8<R: Read>(reader: &mut R) -> Result<u8, CipherError> {
+    let mut bytes = [0u8; 1];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes[0])
+}
+
+fn read_u16<R: Read>(reader: &mut R) -> Result<u16, CipherError> {
+    let mut bytes = [0u8; 2];
+    reader.read_exact(&mut bytes)?;
+    Ok(u16::from_be_bytes(bytes))
+}
+
+fn read_u32<R: Read>(reader: &mut R) -> Result<u32, CipherError> {
+    let mut bytes = [0u8; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_be_bytes(bytes))
+}
+
+fn read_u64<R: Read>(reader: &mut R) -> Result<u64, CipherError> {
+    let mut bytes = [0u8; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_be_bytes(bytes))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::cipher_tools::defaults::{bn256_cipher_params_3, Bn256CipherParams3};
+    use crate::cipher_tools::generate_cipher_params_with_alpha;
+    use crate::tests::init_rng;
+    use franklin_crypto::bellman::pairing::bn256::Bn256;
+
+    // magic, version, two moduli with their lengths, SIZE, RNUMBER and alpha
+    const HEADER_LENGTH: usize = 4 + 1 + 2 + 32 + 2 + 32 + 4 + 4 + 8;
+    const ELEMENT_LENGTH: usize = 32;
+
+    #[test]
+    fn test_binary_round_trip() {
+        let params = bn256_cipher_params_3().unwrap();
+        let bytes = params.to_bytes();
+        assert_eq!(bytes.len(), HEADER_LENGTH + (2 * 3 + 29 * 3) * ELEMENT_LENGTH);
+
+        let decoded = Bn256CipherParams3::from_bytes(&bytes).unwrap();
+        assert_eq!(decoded.vect_for_matrix, params.vect_for_matrix);
+        assert_eq!(decoded.round_constants, params.round_constants);
+    }
+
+    #[test]
+    fn test_params_dimensions() {
+        let params = bn256_cipher_params_3().unwrap();
+        assert_eq!(params_dimensions(&params.to_bytes()).unwrap(), (3, 29));
+        assert_eq!(params_dimensions(params.to_json().as_bytes()).unwrap(), (3, 29));
+        assert!(params_dimensions(b"RBCQ").is_err());
+    }
+
+    #[test]
+    fn test_json_round_trip() {
+        let params = bn256_cipher_params_3().unwrap();
+        let json = params.to_json();
+
+        let decoded = Bn256CipherParams3::from_json(&json).unwrap();
+        assert_eq!(decoded.vect_for_matrix, params.vect_for_matrix);
+        assert_eq!(decoded.round_constants, params.round_constants);
+    }
+
+    #[test]
+    fn test_alpha_round_trip() {
+        let params: CipherParams<Bn256, 3, 7> = generate_cipher_params_with_alpha(&mut init_rng(), 7).unwrap();
+
+        let decoded = CipherParams::<Bn256, 3, 7>::from_bytes(&params.to_bytes()).unwrap();
+        assert_eq!(decoded.alpha(), 7);
+        assert_eq!(decoded.sbox2.alpha_inv, params.sbox2.alpha_inv);
+
+        let decoded = CipherParams::<Bn256, 3, 7>::from_json(&params.to_json()).unwrap();
+        assert_eq!(decoded.alpha(), 7);
+    }
+
+    #[test]
+    fn test_invalid_binary_is_rejected() {
+        let bytes = bn256_cipher_params_3().unwrap().to_bytes();
+
+        let mut wrong_magic = bytes.clone();
+        wrong_magic[0] = b'X';
+        assert!(matches!(Bn256CipherParams3::from_bytes(&wrong_magic), Err(CipherError::InvalidEncoding(_))));
+
+        let mut wrong_version = bytes.clone();
+        wrong_version[4] = PARAMS_FORMAT_VERSION + 1;
+        assert!(matches!(Bn256CipherParams3::from_bytes(&wrong_version), Err(CipherError::InvalidEncoding(_))));
+
+        assert!(matches!(CipherParams::<Bn256, 4, 29>::from_bytes(&bytes), Err(CipherError::UnsupportedSize(3))));
+        assert!(matches!(CipherParams::<Bn256, 3, 31>::from_bytes(&bytes), Err(CipherError::InvalidNumberOfRounds(29))));
+
+        let mut even_alpha = bytes.clone();
+        even_alpha[HEADER_LENGTH - 1] = 4;
+        assert!(matches!(Bn256CipherParams3::from_bytes(&even_alpha), Err(CipherError::InvalidAlpha(4))));
+
+        let mut duplicated_vector_element = bytes.clone();
+        let (first, second) = (HEADER_LENGTH, HEADER_LENGTH + ELEMENT_LENGTH);
+        duplicated_vector_element.copy_within(first..second, second);
+        assert!(matches!(Bn256CipherParams3::from_bytes(&duplicated_vector_element), Err(CipherError::InvalidMdsVectors)));
+
+        let mut zero_constant = bytes.clone();
+        let length = zero_constant.len();
+        for byte in zero_constant[(length - ELEMENT_LENGTH)..].iter_mut() {
+            *byte = 0;
+        }
+        assert!(matches!(Bn256CipherParams3::from_bytes(&zero_constant), Err(CipherError::InvalidEncoding(_))));
+
+        let mut non_canonical = bytes.clone();
+        for byte in non_canonical[(length - ELEMENT_LENGTH)..].iter_mut() {
+            *byte = 0xff;
+        }
+        assert!(matches!(Bn256CipherParams3::from_bytes(&non_canonical), Err(CipherError::InvalidEncoding(_))));
+
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        assert!(matches!(Bn256CipherParams3::from_bytes(&trailing), Err(CipherError::InvalidEncoding(_))));
+
+        assert!(matches!(Bn256CipherParams3::from_bytes(&bytes[..(length - 1)]), Err(CipherError::Io(_))));
+    }
+
+    #[test]
+    fn test_invalid_json_is_rejected() {
+        let json = bn256_cipher_params_3().unwrap().to_json();
+        assert!(matches!(CipherParams::<Bn256, 4, 29>::from_json(&json), Err(CipherError::UnsupportedSize(3))));
+
+        let mut decoded: serde_json::Value = serde_json::from_str(&json).unwrap();
+        decoded["round_constants"][0][0] = serde_json::Value::String("zz".to_string());
+        assert!(matches!(Bn256CipherParams3::from_json(&decoded.to_string()), Err(CipherError::InvalidEncoding(_))));
+
+        let mut decoded: serde_json::Value = serde_json::from_str(&json).unwrap();
+        decoded["mds_vectors"][1][0] = decoded["mds_vectors"][0][0].clone();
+        assert!(matches!(Bn256CipherParams3::from_json(&decoded.to_string()), Err(CipherError::InvalidMdsVectors)));
+
+        let mut decoded: serde_json::Value = serde_json::from_str(&json).unwrap();
+        decoded["round_constants"].as_array_mut().unwrap().pop();
+        assert!(matches!(Bn256CipherParams3::from_json(&decoded.to_string()), Err(CipherError::InvalidEncoding(_))));
+    }
+}
@@ -0,0 +1,194 @@
+use franklin_crypto::plonk::circuit::allocated_num::Num;
+use franklin_crypto::plonk::circuit::Width4WithCustomGates;
+use franklin_crypto::bellman::pairing::Engine;
+use franklin_crypto::bellman::plonk::better_better_cs::cs::{
+    ConstraintSystem, PlonkCsWidth4WithNextStepParams, SetupAssembly, Width4MainGateWithDNext
+};
+use super::cipher_tools::{CipherParams, mds::nums_into_lcs};
+use super::enc_dec::{
+    construct_ready_params, KeySchedule, encryption_round, decryption_round,
+    rescue_encryption, rescue_decryption,
+    rescue_encryption_with_key_schedule, rescue_decryption_with_key_schedule
+};
+use super::error::CipherError;
+
+// Number of gates of every part of the cipher. Rounds alternate between
+// the alpha and the inverse alpha S-box, which cost differently, so both
+// kinds are reported. Encryption without a key schedule includes its cost.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CipherCostReport {
+    pub size: usize,
+    pub rounds: usize,
+    pub alpha_sbox_layer: usize,
+    pub inverse_alpha_sbox_layer: usize,
+    pub alpha_round: usize,
+    pub inverse_alpha_round: usize,
+    pub key_schedule: usize,
+    pub encryption_with_key_schedule: usize,
+    pub decryption_with_key_schedule: usize,
+    pub encryption: usize,
+    pub decryption: usize
+}
+
+impl CipherCostReport {
+    pub fn components(&self) -> Vec<(&'static str, usize)> {
+        vec![
+            ("alpha S-box layer", self.alpha_sbox_layer),
+            ("inverse alpha S-box layer", self.inverse_alpha_sbox_layer),
+            ("alpha round", self.alpha_round),
+            ("inverse alpha round", self.inverse_alpha_round),
+            ("key schedule", self.key_schedule),
+            ("encryption with schedule", self.encryption_with_key_schedule),
+            ("decryption with schedule", self.decryption_with_key_schedule),
+            ("encryption", self.encryption),
+            ("decryption", self.decryption)
+        ]
+    }
+}
+
+// Gate counts with the plain width 4 main gate. Components are synthesized
+// in setup mode, so no witness is needed.
+pub fn cipher_cost_report<
+    E: Engine,
+    const SIZE: usize,
+    const RNUMBER: usize>(
+    params: &CipherParams<E, SIZE, RNUMBER>
+)-> Result<CipherCostReport, CipherError> {
+    let mut cs = SetupAssembly::<E, PlonkCsWidth4WithNextStepParams, Width4MainGateWithDNext>::new();
+    synthesize_cost_report(&mut cs, params)
+}
+
+// Same with the custom gate for the fifth power.
+pub fn cipher_cost_report_with_custom_gates<
+    E: Engine,
+    const SIZE: usize,
+    const RNUMBER: usize>(
+    params: &CipherParams<E, SIZE, RNUMBER>
+)-> Result<CipherCostReport, CipherError> {
+    let mut cs = SetupAssembly::<E, Width4WithCustomGates, Width4MainGateWithDNext>::new();
+    synthesize_cost_report(&mut cs, params)
+}
+
+pub fn synthesize_cost_report<
+    E: Engine,
+    CS: ConstraintSystem<E>,
+    const SIZE: usize,
+    const RNUMBER: usize>(
+    cs: &mut CS,
+    params: &CipherParams<E, SIZE, RNUMBER>
+)-> Result<CipherCostReport, CipherError> {
+    if RNUMBER < 3 {
+        return Err(CipherError::InvalidNumberOfRounds(RNUMBER));
+    }
+    let ready_params = construct_ready_params(params)?;
+    let key = alloc_block(cs)?;
+    let plaintext = alloc_block(cs)?;
+
+    let mut state = alloc_block(cs)?;
+    let start = cs.get_current_step_number();
+    ready_params.sbox1.apply(cs, &mut state)?;
+    let alpha_sbox_layer = cs.get_current_step_number() - start;
+
+    let mut state = alloc_block(cs)?;
+    let start = cs.get_current_step_number();
+    ready_params.sbox2.apply(cs, &mut state)?;
+    let inverse_alpha_sbox_layer = cs.get_current_step_number() - start;
+
+    let start = cs.get_current_step_number();
+    let key_schedule = KeySchedule::new(cs, &ready_params, &key)?;
+    let key_schedule_gates = cs.get_current_step_number() - start;
+
+    let state = nums_into_lcs(&alloc_block(cs)?);
+    let start = cs.get_current_step_number();
+    let _ = encryption_round(cs, &ready_params, state, &key_schedule.subkeys[1], 1)?;
+    let alpha_round = cs.get_current_step_number() - start;
+
+    let state = nums_into_lcs(&alloc_block(cs)?);
+    let start = cs.get_current_step_number();
+    let _ = encryption_round(cs, &ready_params, state, &key_schedule.subkeys[2], 2)?;
+    let inverse_alpha_round = cs.get_current_step_number() - start;
+
+    let start = cs.get_current_step_number();
+    let ciphertext = rescue_encryption_with_key_schedule(cs, &ready_params, &key_schedule, &plaintext)?;
+    let encryption_with_key_schedule = cs.get_current_step_number() - start;
+
+    let start = cs.get_current_step_number();
+    let _ = rescue_decryption_with_key_schedule(cs, &ready_params, &key_schedule, &ciphertext)?;
+    let decryption_with_key_schedule = cs.get_current_step_number() - start;
+
+    let start = cs.get_current_step_number();
+    let _ = rescue_encryption(cs, &ready_params, &key, &plaintext)?;
+    let encryption = cs.get_current_step_number() - start;
+
+    let start = cs.get_current_step_number();
+    let _ = rescue_decryption(cs, &ready_params, &key, &ciphertext)?;
+    let decryption = cs.get_current_step_number() - start;
+
+    Ok(CipherCostReport {
+        size: SIZE,
+        rounds: RNUMBER,
+        alpha_sbox_layer,
+        inverse_alpha_sbox_layer,
+        alpha_round,
+        inverse_alpha_round,
+        key_schedule: key_schedule_gates,
+        encryption_with_key_schedule,
+        decryption_with_key_schedule,
+        encryption,
+        decryption
+    })
+}
+
+fn alloc_block<E: Engine, CS: ConstraintSystem<E>, const SIZE: usize>(cs: &mut CS) -> Result<[Num<E>; SIZE], CipherError> {
+    let mut block = [Num::<E>::zero(); SIZE];
+    for element in block.iter_mut() {
+        *element = Num::alloc(cs, None)?;
+    }
+    Ok(block)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::cipher_tools::generate_cipher_params;
+    use crate::tests::{init_cs, init_rng, test_inputs};
+    use franklin_crypto::bellman::pairing::bn256::Bn256;
+
+    #[test]
+    fn test_cost_report_is_consistent() {
+        const SIZE: usize = 3;
+        const RNUMBER: usize = 7;
+        let rng = &mut init_rng();
+        let params = generate_cipher_params::<Bn256, _, SIZE, RNUMBER>(rng);
+
+        let report = cipher_cost_report(&params).unwrap();
+        assert_eq!((report.size, report.rounds), (SIZE, RNUMBER));
+        assert_eq!(report.encryption, report.key_schedule + report.encryption_with_key_schedule);
+        assert_eq!(report.decryption, report.key_schedule + report.decryption_with_key_schedule);
+        assert!(report.alpha_round >= report.alpha_sbox_layer);
+        assert!(report.inverse_alpha_round >= report.inverse_alpha_sbox_layer);
+
+        // the report counts the same gates as a synthesis with witnesses
+        let cs = &mut init_cs::<Bn256>();
+        let key = test_inputs::<Bn256, SIZE>(rng);
+        let plaintext = test_inputs::<Bn256, SIZE>(rng);
+        let mut key_as_nums = [Num::<Bn256>::zero(); SIZE];
+        let mut plaintext_as_nums = [Num::<Bn256>::zero(); SIZE];
+        for i in 0..SIZE {
+            key_as_nums[i] = Num::alloc(cs, Some(key[i])).unwrap();
+            plaintext_as_nums[i] = Num::alloc(cs, Some(plaintext[i])).unwrap();
+        }
+        let ready_params = construct_ready_params(&params).unwrap();
+        let start = cs.n();
+        let _ = rescue_encryption(cs, &ready_params, &key_as_nums, &plaintext_as_nums).unwrap();
+        assert_eq!(cs.n() - start, report.encryption);
+
+        let custom_report = cipher_cost_report_with_custom_gates(&params).unwrap();
+        assert!(custom_report.encryption <= report.encryption);
+
+        assert!(matches!(
+            cipher_cost_report(&generate_cipher_params::<Bn256, _, SIZE, 2>(rng)),
+            Err(CipherError::InvalidNumberOfRounds(2))
+        ));
+    }
+}
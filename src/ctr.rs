@@ -0,0 +1,187 @@
+use franklin_crypto::plonk::circuit::allocated_num::Num;
+use franklin_crypto::bellman::pairing::Engine;
+use franklin_crypto::bellman::pairing::ff::Field;
+use franklin_crypto::bellman::plonk::better_better_cs::cs::ConstraintSystem;
+use super::cipher_tools::u64_to_fe;
+use super::enc_dec::{ReadyCipherParams, KeySchedule, rescue_encryption_with_key_schedule};
+use super::native::NativeKeySchedule;
+use super::error::CipherError;
+
+// Counter mode: block i of the message is masked by the encryption of the
+// counter block [nonce, i, 0, .., 0]. The last block of the keystream is
+// truncated to the length of the message, so messages of any length are
+// supported. A nonce must never be reused under the same key.
+pub fn ctr_encryption_native<
+    E: Engine,
+    const SIZE: usize,
+    const RNUMBER: usize>(
+    key_schedule: &NativeKeySchedule<E, SIZE, RNUMBER>,
+    nonce: &E::Fr,
+    message: &[E::Fr])->Result<Vec<E::Fr>, CipherError>{
+
+    let mut ciphertext = Vec::with_capacity(message.len());
+    for (counter, chunk) in message.chunks(SIZE).enumerate() {
+        let block = counter_block::<E, SIZE>(nonce, counter as u64)?;
+        let keystream = key_schedule.encrypt(&block);
+        for (m, k) in chunk.iter().zip(keystream.iter()) {
+            let mut c = *m;
+            c.add_assign(k);
+            ciphertext.push(c);
+        }
+    }
+    Ok(ciphertext)
+}
+
+pub fn ctr_decryption_native<
+    E: Engine,
+    const SIZE: usize,
+    const RNUMBER: usize>(
+    key_schedule: &NativeKeySchedule<E, SIZE, RNUMBER>,
+    nonce: &E::Fr,
+    ciphertext: &[E::Fr])->Result<Vec<E::Fr>, CipherError>{
+
+    let mut message = Vec::with_capacity(ciphertext.len());
+    for (counter, chunk) in ciphertext.chunks(SIZE).enumerate() {
+        let block = counter_block::<E, SIZE>(nonce, counter as u64)?;
+        let keystream = key_schedule.encrypt(&block);
+        for (c, k) in chunk.iter().zip(keystream.iter()) {
+            let mut m = *c;
+            m.sub_assign(k);
+            message.push(m);
+        }
+    }
+    Ok(message)
+}
+
+pub fn ctr_encryption<
+    E: Engine,
+    CS: ConstraintSystem<E>,
+    const SIZE: usize,
+    const RNUMBER: usize>(
+    cs: &mut CS,
+    params: &ReadyCipherParams<E, SIZE, RNUMBER>,
+    key_schedule: &KeySchedule<E, SIZE, RNUMBER>,
+    nonce: &Num<E>,
+    message: &[Num<E>])->Result<Vec<Num<E>>, CipherError>{
+
+    let mut ciphertext = Vec::with_capacity(message.len());
+    for (counter, chunk) in message.chunks(SIZE).enumerate() {
+        let block = counter_block_num::<E, SIZE>(nonce, counter as u64)?;
+        let keystream = rescue_encryption_with_key_schedule(cs, params, key_schedule, &block)?;
+        for (m, k) in chunk.iter().zip(keystream.iter()) {
+            ciphertext.push(m.add(cs, k)?);
+        }
+    }
+    Ok(ciphertext)
+}
+
+pub fn ctr_decryption<
+    E: Engine,
+    CS: ConstraintSystem<E>,
+    const SIZE: usize,
+    const RNUMBER: usize>(
+    cs: &mut CS,
+    params: &ReadyCipherParams<E, SIZE, RNUMBER>,
+    key_schedule: &KeySchedule<E, SIZE, RNUMBER>,
+    nonce: &Num<E>,
+    ciphertext: &[Num<E>])->Result<Vec<Num<E>>, CipherError>{
+
+    let mut message = Vec::with_capacity(ciphertext.len());
+    for (counter, chunk) in ciphertext.chunks(SIZE).enumerate() {
+        let block = counter_block_num::<E, SIZE>(nonce, counter as u64)?;
+        let keystream = rescue_encryption_with_key_schedule(cs, params, key_schedule, &block)?;
+        for (c, k) in chunk.iter().zip(keystream.iter()) {
+            message.push(c.sub(cs, k)?);
+        }
+    }
+    Ok(message)
+}
+
+// Counter mode needs room for both the nonce and the counter.
+fn counter_block<E: Engine, const SIZE: usize>(nonce: &E::Fr, counter: u64) -> Result<[E::Fr; SIZE], CipherError> {
+    if SIZE < 2 {
+        return Err(CipherError::UnsupportedSize(SIZE));
+    }
+    let mut block = [E::Fr::zero(); SIZE];
+    block[0] = *nonce;
+    block[1] = u64_to_fe::<E>(counter);
+    Ok(block)
+}
+
+fn counter_block_num<E: Engine, const SIZE: usize>(nonce: &Num<E>, counter: u64) -> Result<[Num<E>; SIZE], CipherError> {
+    if SIZE < 2 {
+        return Err(CipherError::UnsupportedSize(SIZE));
+    }
+    let mut block = [Num::<E>::zero(); SIZE];
+    block[0] = *nonce;
+    block[1] = Num::Constant(u64_to_fe::<E>(counter));
+    Ok(block)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::cipher_tools::generate_cipher_params;
+    use crate::enc_dec::construct_ready_params;
+    use crate::tests::{init_cs, init_rng};
+    use franklin_crypto::bellman::pairing::bn256::{Bn256, Fr};
+    use rand::Rand;
+
+    #[test]
+    fn test_ctr_round_trip() {
+        const SIZE: usize = 3;
+        const RNUMBER: usize = 7;
+        let rng = &mut init_rng();
+        let params = generate_cipher_params::<Bn256, _, SIZE, RNUMBER>(rng);
+        let key = [Fr::rand(rng), Fr::rand(rng), Fr::rand(rng)];
+        let key_schedule = NativeKeySchedule::new(&params, &key).unwrap();
+        let nonce = Fr::rand(rng);
+
+        for length in [1, SIZE, 2 * SIZE + 1].iter() {
+            let message: Vec<Fr> = (0..*length).map(|_| Fr::rand(rng)).collect();
+            let ciphertext = ctr_encryption_native(&key_schedule, &nonce, &message).unwrap();
+            assert_eq!(ciphertext.len(), message.len());
+            assert_ne!(ciphertext, message);
+            assert_eq!(ctr_decryption_native(&key_schedule, &nonce, &ciphertext).unwrap(), message);
+
+            let other_nonce = Fr::rand(rng);
+            assert_ne!(ctr_encryption_native(&key_schedule, &other_nonce, &message).unwrap(), ciphertext);
+        }
+    }
+
+    #[test]
+    fn test_ctr_native_matches_circuit() {
+        const SIZE: usize = 3;
+        const RNUMBER: usize = 7;
+        let rng = &mut init_rng();
+        let cs = &mut init_cs::<Bn256>();
+        let params = generate_cipher_params::<Bn256, _, SIZE, RNUMBER>(rng);
+        let key = [Fr::rand(rng), Fr::rand(rng), Fr::rand(rng)];
+        let nonce = Fr::rand(rng);
+        let message: Vec<Fr> = (0..(2 * SIZE + 1)).map(|_| Fr::rand(rng)).collect();
+
+        let native_key_schedule = NativeKeySchedule::new(&params, &key).unwrap();
+        let expected = ctr_encryption_native(&native_key_schedule, &nonce, &message).unwrap();
+
+        let mut key_as_nums = [Num::<Bn256>::zero(); SIZE];
+        for i in 0..SIZE {
+            key_as_nums[i] = Num::alloc(cs, Some(key[i])).unwrap();
+        }
+        let nonce_as_num = Num::alloc(cs, Some(nonce)).unwrap();
+        let message_as_nums: Vec<Num<Bn256>> = message.iter().map(|m| Num::alloc(cs, Some(*m)).unwrap()).collect();
+
+        let ready_params = construct_ready_params(&params).unwrap();
+        let key_schedule = KeySchedule::new(cs, &ready_params, &key_as_nums).unwrap();
+        let ciphertext = ctr_encryption(cs, &ready_params, &key_schedule, &nonce_as_num, &message_as_nums).unwrap();
+        let decrypted = ctr_decryption(cs, &ready_params, &key_schedule, &nonce_as_num, &ciphertext).unwrap();
+
+        assert_eq!(ciphertext.len(), message.len());
+        for i in 0..message.len() {
+            assert_eq!(ciphertext[i].get_value().unwrap(), expected[i]);
+            assert_eq!(decrypted[i].get_value().unwrap(), message[i]);
+        }
+
+        cs.finalize();
+        assert!(cs.is_satisfied());
+    }
+}
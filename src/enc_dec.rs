@@ -1,12 +1,18 @@
 use franklin_crypto::plonk::circuit::allocated_num::Num;
+use franklin_crypto::plonk::circuit::boolean::Boolean;
+use franklin_crypto::plonk::circuit::linear_combination::LinearCombination;
 use franklin_crypto::bellman::pairing::Engine;
 use franklin_crypto::bellman::plonk::better_better_cs::cs::ConstraintSystem;
+use franklin_crypto::bellman::SynthesisError;
 use super::cipher_tools::{
     CipherParams,
-    mds::{MdsMatrix, construct_mds_matrix, construct_inverse_matrix, dot_product, add_vectors, sub_vectors},
-    sboxes::{QuinticSBox, QuinticInverseSBox}
+    mds::{
+        MdsMatrix, construct_mds_matrix, construct_inverse_matrix, matrix_vector_product,
+        add_vectors, sub_vectors, nums_into_lcs, lcs_into_nums
+    },
+    sboxes::{AlphaSBox, AlphaInverseSBox}
 };
-use std::marker::PhantomData;
+use super::error::CipherError;
 
 pub struct ReadyCipherParams<
     E: Engine,
@@ -14,39 +20,68 @@ pub struct ReadyCipherParams<
     const RNUMBER: usize> {
     pub matrix: MdsMatrix<E, SIZE>,
     pub inv_matrix: MdsMatrix<E, SIZE>,
-    pub sbox1: QuinticSBox<E, SIZE>,
-    pub sbox2: QuinticInverseSBox<E, SIZE>,
+    pub sbox1: AlphaSBox<E, SIZE>,
+    pub sbox2: AlphaInverseSBox<E, SIZE>,
     pub round_constants: [[Num<E>; SIZE]; RNUMBER]
 }
 
+// All parameters are embedded as circuit constants, so they are fixed by the
+// verification key and can not be chosen by the prover.
 pub fn construct_ready_params<
     E: Engine, 
-    CS: ConstraintSystem<E>, 
     const SIZE: usize,
     const RNUMBER: usize>(
-    cs: &mut CS,
-    params: &mut CipherParams<E, SIZE, RNUMBER>
-    )-> ReadyCipherParams<E, SIZE, RNUMBER>{
-    let matrix = construct_mds_matrix::<E, CS, SIZE>(cs, &mut params.vect_for_matrix);
-    let inv_matrix = construct_inverse_matrix::<E, CS, SIZE>(cs, &matrix).unwrap();
-    let sbox1 = QuinticSBox::<E, SIZE>{
-        _marker: PhantomData::<E>::default()
-    };
-    let sbox2 = QuinticInverseSBox::<E, SIZE>{
-        _marker: PhantomData::<E>::default()
-    };
+    params: &CipherParams<E, SIZE, RNUMBER>
+    )-> Result<ReadyCipherParams<E, SIZE, RNUMBER>, CipherError>{
+    params.check()?;
+    let matrix = construct_mds_matrix::<E, SIZE>(&params.vect_for_matrix)?;
+    let inv_matrix = construct_inverse_matrix::<E, SIZE>(&params.vect_for_matrix)?;
+    let sbox1 = AlphaSBox::<E, SIZE>::new(params.alpha())?;
+    let sbox2 = AlphaInverseSBox::<E, SIZE>::new(params.alpha())?;
     let mut round_constants = [[Num::<E>::zero(); SIZE]; RNUMBER];
     for i in 0..RNUMBER {
         for j in 0..SIZE {
-            round_constants[i][j] = Num::alloc(cs, Some(params.round_constants[i][j])).unwrap();
+            round_constants[i][j] = Num::Constant(params.round_constants[i][j]);
         }
     }
-    ReadyCipherParams {
+    Ok(ReadyCipherParams {
         matrix,
         inv_matrix,
         sbox1,
         sbox2,
         round_constants
+    })
+}
+
+pub struct KeySchedule<
+    E: Engine,
+    const SIZE: usize,
+    const RNUMBER: usize> {
+    pub subkeys: [[Num<E>; SIZE]; RNUMBER]
+}
+
+impl<E: Engine, const SIZE: usize, const RNUMBER: usize> KeySchedule<E, SIZE, RNUMBER> {
+    pub fn new<CS: ConstraintSystem<E>>(
+        cs: &mut CS,
+        params: &ReadyCipherParams<E, SIZE, RNUMBER>,
+        key: &[Num<E>; SIZE])->Result<Self, CipherError>{
+        let subkeys = construct_subkeys(cs, params, *key)?;
+        Ok(KeySchedule {
+            subkeys
+        })
+    }
+
+    // Circuit version of `NativeKeySchedule::tweaked`, costs SIZE gates per
+    // round on top of the untweaked schedule.
+    pub fn tweaked<CS: ConstraintSystem<E>>(
+        &self,
+        cs: &mut CS,
+        params: &ReadyCipherParams<E, SIZE, RNUMBER>,
+        tweak: &Num<E>)->Result<Self, CipherError>{
+        let subkeys = tweak_subkeys(cs, params, &self.subkeys, tweak)?;
+        Ok(KeySchedule {
+            subkeys
+        })
     }
 }
 
@@ -58,61 +93,213 @@ pub fn rescue_encryption<
     cs: &mut CS,
     params: &ReadyCipherParams<E, SIZE, RNUMBER>, 
     key: &[Num<E>; SIZE], 
-    plaintext: &[Num<E>; SIZE])->[Num<E>; SIZE]{
+    plaintext: &[Num<E>; SIZE])->Result<[Num<E>; SIZE], CipherError>{
+
+    let key_schedule = KeySchedule::new(cs, params, key)?;
+    rescue_encryption_with_key_schedule(cs, params, &key_schedule, plaintext)
+}
+
+pub fn rescue_decryption<
+    E: Engine, 
+    CS: ConstraintSystem<E>, 
+    const SIZE: usize,
+    const RNUMBER: usize>(
+    cs: &mut CS,
+    params: &ReadyCipherParams<E, SIZE, RNUMBER>, 
+    key: &[Num<E>; SIZE], 
+    ciphertext: &[Num<E>; SIZE])->Result<[Num<E>; SIZE], CipherError>{
+
+    let key_schedule = KeySchedule::new(cs, params, key)?;
+    rescue_decryption_with_key_schedule(cs, params, &key_schedule, ciphertext)
+}
+
+pub fn rescue_tweakable_encryption<
+    E: Engine, 
+    CS: ConstraintSystem<E>, 
+    const SIZE: usize,
+    const RNUMBER: usize>(
+    cs: &mut CS,
+    params: &ReadyCipherParams<E, SIZE, RNUMBER>, 
+    key: &[Num<E>; SIZE], 
+    tweak: &Num<E>,
+    plaintext: &[Num<E>; SIZE])->Result<[Num<E>; SIZE], CipherError>{
+
+    let key_schedule = KeySchedule::new(cs, params, key)?.tweaked(cs, params, tweak)?;
+    rescue_encryption_with_key_schedule(cs, params, &key_schedule, plaintext)
+}
+
+pub fn rescue_tweakable_decryption<
+    E: Engine, 
+    CS: ConstraintSystem<E>, 
+    const SIZE: usize,
+    const RNUMBER: usize>(
+    cs: &mut CS,
+    params: &ReadyCipherParams<E, SIZE, RNUMBER>, 
+    key: &[Num<E>; SIZE], 
+    tweak: &Num<E>,
+    ciphertext: &[Num<E>; SIZE])->Result<[Num<E>; SIZE], CipherError>{
+
+    let key_schedule = KeySchedule::new(cs, params, key)?.tweaked(cs, params, tweak)?;
+    rescue_decryption_with_key_schedule(cs, params, &key_schedule, ciphertext)
+}
+
+pub fn rescue_encryption_with_key_schedule<
+    E: Engine, 
+    CS: ConstraintSystem<E>, 
+    const SIZE: usize,
+    const RNUMBER: usize>(
+    cs: &mut CS,
+    params: &ReadyCipherParams<E, SIZE, RNUMBER>, 
+    key_schedule: &KeySchedule<E, SIZE, RNUMBER>, 
+    plaintext: &[Num<E>; SIZE])->Result<[Num<E>; SIZE], CipherError>{
 	
-    let subkeys = construct_subkeys(cs, &params, *key);
+    let subkeys = &key_schedule.subkeys;
 
-    let mut ciphertext = add_vectors(cs, &plaintext, &subkeys[0]);
-    let mut helptext = ciphertext;
-    let matrix = &params.matrix;
+    let mut ciphertext = nums_into_lcs(plaintext);
+    add_vectors(&mut ciphertext, &subkeys[0]);
 
     for i in 1..RNUMBER {
-        for j in 0..SIZE {
-            helptext[j] = dot_product(cs, &ciphertext, &matrix.get_row(j));
-        }
-        ciphertext = helptext;
-        if i%2 == 1 {
-            params.sbox1.apply(cs, &mut ciphertext);
-        } else {
-            params.sbox2.apply(cs, &mut ciphertext);
-        }
-        ciphertext = add_vectors(cs, &ciphertext, &subkeys[i]);
+        ciphertext = encryption_round(cs, params, ciphertext, &subkeys[i], i)?;
     }
-    ciphertext
+    lcs_into_nums(cs, ciphertext)
 }
 
-pub fn rescue_decryption<
+pub fn rescue_decryption_with_key_schedule<
     E: Engine, 
     CS: ConstraintSystem<E>, 
     const SIZE: usize,
     const RNUMBER: usize>(
     cs: &mut CS,
     params: &ReadyCipherParams<E, SIZE, RNUMBER>, 
-    key: &[Num<E>; SIZE], 
-    ciphertext: &[Num<E>; SIZE])->[Num<E>; SIZE]{
+    key_schedule: &KeySchedule<E, SIZE, RNUMBER>, 
+    ciphertext: &[Num<E>; SIZE])->Result<[Num<E>; SIZE], CipherError>{
 
-    let subkeys = construct_subkeys(cs, &params, *key);
+    let subkeys = &key_schedule.subkeys;
 
-    let mut plaintext = sub_vectors(cs, &ciphertext, &subkeys[RNUMBER-1]);
-    let mut helptext = plaintext;
-    let matrix = &params.inv_matrix;
+    let mut plaintext = nums_into_lcs(ciphertext);
+    sub_vectors(&mut plaintext, &subkeys[RNUMBER-1]);
 
     for i in 1..RNUMBER {
+        plaintext = decryption_round(cs, params, plaintext, &subkeys[RNUMBER-i-1], RNUMBER-i)?;
+    }
+    lcs_into_nums(cs, plaintext)
+}
 
-        if i%2 == 1 {
-            params.sbox1.apply(cs, &mut plaintext);
-        } else {
-            params.sbox2.apply(cs, &mut plaintext);
-        }
-        
-        for j in 0..SIZE {
-            helptext[j] = dot_product(cs, &plaintext, &matrix.get_row(j));
-        }
-        plaintext = helptext;
-                
-        plaintext = sub_vectors(cs, &plaintext, &subkeys[RNUMBER-i-1]);
+// Round `round` of encryption: the MDS matrix, the S-box layer and the
+// subkey. The state stays a linear combination between rounds, so the
+// matrix and the subkeys only cost gates when the state is collapsed
+// before the S-boxes.
+pub fn encryption_round<
+    E: Engine, 
+    CS: ConstraintSystem<E>, 
+    const SIZE: usize,
+    const RNUMBER: usize>(
+    cs: &mut CS,
+    params: &ReadyCipherParams<E, SIZE, RNUMBER>, 
+    mut state: [LinearCombination<E>; SIZE],
+    subkey: &[Num<E>; SIZE],
+    round: usize)->Result<[LinearCombination<E>; SIZE], CipherError>{
+
+    matrix_vector_product(&params.matrix, &mut state);
+    let mut elements = lcs_into_nums(cs, state)?;
+    if round%2 == 1 {
+        params.sbox1.apply(cs, &mut elements)?;
+    } else {
+        params.sbox2.apply(cs, &mut elements)?;
+    }
+    let mut state = nums_into_lcs(&elements);
+    add_vectors(&mut state, subkey);
+    Ok(state)
+}
+
+// Undoes encryption round `round`, `subkey` is the one of the round before.
+pub fn decryption_round<
+    E: Engine, 
+    CS: ConstraintSystem<E>, 
+    const SIZE: usize,
+    const RNUMBER: usize>(
+    cs: &mut CS,
+    params: &ReadyCipherParams<E, SIZE, RNUMBER>, 
+    state: [LinearCombination<E>; SIZE],
+    subkey: &[Num<E>; SIZE],
+    round: usize)->Result<[LinearCombination<E>; SIZE], CipherError>{
+
+    let mut elements = lcs_into_nums(cs, state)?;
+    if round%2 == 1 {
+        params.sbox2.apply(cs, &mut elements)?;
+    } else {
+        params.sbox1.apply(cs, &mut elements)?;
     }
-    plaintext
+    let mut state = nums_into_lcs(&elements);
+    matrix_vector_product(&params.inv_matrix, &mut state);
+    sub_vectors(&mut state, subkey);
+    Ok(state)
+}
+
+// Returns the ciphertext if the flag is set and the plaintext otherwise. The
+// encryption is synthesized in both cases, so the number of constraints does
+// not depend on the flag.
+pub fn rescue_encryption_conditional<
+    E: Engine, 
+    CS: ConstraintSystem<E>, 
+    const SIZE: usize,
+    const RNUMBER: usize>(
+    cs: &mut CS,
+    params: &ReadyCipherParams<E, SIZE, RNUMBER>, 
+    key: &[Num<E>; SIZE], 
+    plaintext: &[Num<E>; SIZE],
+    flag: &Boolean)->Result<[Num<E>; SIZE], CipherError>{
+
+    let key_schedule = KeySchedule::new(cs, params, key)?;
+    rescue_encryption_conditional_with_key_schedule(cs, params, &key_schedule, plaintext, flag)
+}
+
+pub fn rescue_encryption_conditional_with_key_schedule<
+    E: Engine, 
+    CS: ConstraintSystem<E>, 
+    const SIZE: usize,
+    const RNUMBER: usize>(
+    cs: &mut CS,
+    params: &ReadyCipherParams<E, SIZE, RNUMBER>, 
+    key_schedule: &KeySchedule<E, SIZE, RNUMBER>, 
+    plaintext: &[Num<E>; SIZE],
+    flag: &Boolean)->Result<[Num<E>; SIZE], CipherError>{
+
+    let ciphertext = rescue_encryption_with_key_schedule(cs, params, key_schedule, plaintext)?;
+    select_block(cs, flag, &ciphertext, plaintext)
+}
+
+// Like the conditional encryption, but an unset flag yields the encryption of
+// the dummy block instead of the plaintext, so the output is always a
+// ciphertext. The block is selected before a single encryption, so the cost
+// does not depend on the flag either.
+pub fn rescue_encryption_conditional_with_dummy<
+    E: Engine, 
+    CS: ConstraintSystem<E>, 
+    const SIZE: usize,
+    const RNUMBER: usize>(
+    cs: &mut CS,
+    params: &ReadyCipherParams<E, SIZE, RNUMBER>, 
+    key_schedule: &KeySchedule<E, SIZE, RNUMBER>, 
+    plaintext: &[Num<E>; SIZE],
+    dummy: &[Num<E>; SIZE],
+    flag: &Boolean)->Result<[Num<E>; SIZE], CipherError>{
+
+    let selected = select_block(cs, flag, plaintext, dummy)?;
+    rescue_encryption_with_key_schedule(cs, params, key_schedule, &selected)
+}
+
+fn select_block<E: Engine, CS: ConstraintSystem<E>, const SIZE: usize>(
+    cs: &mut CS,
+    flag: &Boolean,
+    first: &[Num<E>; SIZE],
+    second: &[Num<E>; SIZE])->Result<[Num<E>; SIZE], CipherError>{
+
+    let mut selected = [Num::<E>::zero(); SIZE];
+    for i in 0..SIZE {
+        selected[i] = Num::conditionally_select(cs, flag, &first[i], &second[i])?;
+    }
+    Ok(selected)
 }
 
 fn construct_subkeys<
@@ -122,24 +309,49 @@ fn construct_subkeys<
     const RNUMBER: usize>(
     cs: &mut CS,
     params: &ReadyCipherParams<E, SIZE, RNUMBER>, 
-    key: [Num<E>; SIZE] )->[[Num<E>; SIZE]; RNUMBER]{
+    key: [Num<E>; SIZE] )->Result<[[Num<E>; SIZE]; RNUMBER], CipherError>{
 
     let mut subkeys = [[Num::<E>::zero(); SIZE]; RNUMBER];
-    let raconsts = params.round_constants;
-    let matrix = &params.matrix;
+    let raconsts = &params.round_constants;
 
-    subkeys[0] = add_vectors(cs, &key, &raconsts[0]);
+    let mut state = nums_into_lcs(&key);
+    add_vectors(&mut state, &raconsts[0]);
+    subkeys[0] = lcs_into_nums(cs, state)?;
 
     for i in 1..RNUMBER {
-        for j in 0..SIZE {
-            subkeys[i][j] = dot_product(cs, &subkeys[i-1], &matrix.get_row(j));
-        }
+        let mut state = nums_into_lcs(&subkeys[i-1]);
+        matrix_vector_product(&params.matrix, &mut state);
+        let mut subkey = lcs_into_nums(cs, state)?;
         if i%2 == 1 {
-            params.sbox1.apply(cs, &mut subkeys[i]);
+            params.sbox1.apply(cs, &mut subkey)?;
         } else {
-            params.sbox2.apply(cs, &mut subkeys[i]);
+            params.sbox2.apply(cs, &mut subkey)?;
+        }
+        let mut state = nums_into_lcs(&subkey);
+        add_vectors(&mut state, &raconsts[i]);
+        subkeys[i] = lcs_into_nums(cs, state)?;
+    }
+    Ok(subkeys)
+}
+
+fn tweak_subkeys<
+    E: Engine, 
+    CS: ConstraintSystem<E>, 
+    const SIZE: usize,
+    const RNUMBER: usize>(
+    cs: &mut CS,
+    params: &ReadyCipherParams<E, SIZE, RNUMBER>, 
+    subkeys: &[[Num<E>; SIZE]; RNUMBER],
+    tweak: &Num<E>)->Result<[[Num<E>; SIZE]; RNUMBER], CipherError>{
+
+    let mut tweaked = *subkeys;
+    for i in 1..RNUMBER {
+        let mut state = nums_into_lcs(&subkeys[i]);
+        for j in 0..SIZE {
+            let constant = params.round_constants[i][j].get_value().ok_or(SynthesisError::AssignmentMissing)?;
+            state[j].add_assign_number_with_coeff(tweak, constant);
         }
-        subkeys[i] = add_vectors(cs, &subkeys[i], &raconsts[i]);
+        tweaked[i] = lcs_into_nums(cs, state)?;
     }
-    subkeys
+    Ok(tweaked)
 }
@@ -0,0 +1,59 @@
+use franklin_crypto::bellman::SynthesisError;
+use std::fmt;
+use std::io;
+
+#[derive(Debug)]
+pub enum CipherError {
+    // The vectors of the Cauchy matrix have a wrong length or repeated elements.
+    InvalidMdsVectors,
+    SingularMatrix,
+    UnsupportedSize(usize),
+    // Index of a row past the last row of the matrix.
+    RowOutOfRange(usize),
+    InvalidNumberOfRounds(usize),
+    // gcd(alpha, p - 1) != 1, so x^alpha is not a permutation of the field.
+    InvalidAlpha(u64),
+    InvalidEncoding(String),
+    InvalidHashParams(String),
+    InvalidByteEncoding(String),
+    // A curve point of small order, e.g. the identity, as a public key.
+    InvalidPoint,
+    // The duplex sponge got no input or squeezed no output.
+    Sponge(String),
+    Io(io::Error),
+    Synthesis(SynthesisError)
+}
+
+impl fmt::Display for CipherError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CipherError::InvalidMdsVectors => write!(f, "invalid vectors for the MDS matrix"),
+            CipherError::SingularMatrix => write!(f, "MDS matrix is not invertible"),
+            CipherError::UnsupportedSize(size) => write!(f, "unsupported state size {}", size),
+            CipherError::RowOutOfRange(row) => write!(f, "matrix has no row {}", row),
+            CipherError::InvalidNumberOfRounds(rnumber) => write!(f, "invalid number of rounds {}", rnumber),
+            CipherError::InvalidAlpha(alpha) => write!(f, "alpha {} does not give a permutation of the field", alpha),
+            CipherError::InvalidEncoding(reason) => write!(f, "invalid parameters encoding: {}", reason),
+            CipherError::InvalidHashParams(reason) => write!(f, "hash parameters can not be used by the cipher: {}", reason),
+            CipherError::InvalidByteEncoding(reason) => write!(f, "invalid byte encoding: {}", reason),
+            CipherError::InvalidPoint => write!(f, "point is the identity or not in the prime order subgroup"),
+            CipherError::Sponge(reason) => write!(f, "sponge error: {}", reason),
+            CipherError::Io(err) => write!(f, "io error: {}", err),
+            CipherError::Synthesis(err) => write!(f, "synthesis error: {}", err)
+        }
+    }
+}
+
+impl std::error::Error for CipherError {}
+
+impl From<io::Error> for CipherError {
+    fn from(err: io::Error) -> Self {
+        CipherError::Io(err)
+    }
+}
+
+impl From<SynthesisError> for CipherError {
+    fn from(err: SynthesisError) -> Self {
+        CipherError::Synthesis(err)
+    }
+}
@@ -1,2 +1,14 @@
+pub mod error;
 pub mod enc_dec;
-pub mod cipher_tools;
\ No newline at end of file
+pub mod cipher_tools;
+pub mod native;
+pub mod ctr;
+pub mod aead;
+pub mod pke;
+pub mod proof;
+pub mod bytes;
+pub mod mac;
+pub mod batch;
+pub mod cost;
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,203 @@
+use franklin_crypto::plonk::circuit::allocated_num::Num;
+use franklin_crypto::plonk::circuit::boolean::Boolean;
+use franklin_crypto::bellman::pairing::Engine;
+use franklin_crypto::bellman::pairing::ff::Field;
+use franklin_crypto::bellman::plonk::better_better_cs::cs::ConstraintSystem;
+use super::cipher_tools::u64_to_fe;
+use super::enc_dec::{ReadyCipherParams, KeySchedule, rescue_encryption_with_key_schedule};
+use super::native::NativeKeySchedule;
+use super::error::CipherError;
+
+// PRF on one block: the block is added back to its encryption, so unlike the
+// cipher itself the output can not be inverted with the key.
+pub fn rescue_prf_native<
+    E: Engine,
+    const SIZE: usize,
+    const RNUMBER: usize>(
+    key_schedule: &NativeKeySchedule<E, SIZE, RNUMBER>,
+    input: &[E::Fr; SIZE])->[E::Fr; SIZE]{
+
+    let mut output = key_schedule.encrypt(input);
+    for (o, i) in output.iter_mut().zip(input.iter()) {
+        o.add_assign(i);
+    }
+    output
+}
+
+// CBC-MAC with the message length as the first block, which makes it secure
+// for messages of different lengths. The last block is padded with zeros,
+// the length keeps padded messages apart. The tag is the first element of
+// the last state. The PRF and the MAC should not share a key.
+pub fn rescue_mac_native<
+    E: Engine,
+    const SIZE: usize,
+    const RNUMBER: usize>(
+    key_schedule: &NativeKeySchedule<E, SIZE, RNUMBER>,
+    message: &[E::Fr])->E::Fr{
+
+    let mut state = [E::Fr::zero(); SIZE];
+    state[0] = u64_to_fe::<E>(message.len() as u64);
+    state = key_schedule.encrypt(&state);
+
+    for chunk in message.chunks(SIZE) {
+        for (s, m) in state.iter_mut().zip(chunk.iter()) {
+            s.add_assign(m);
+        }
+        state = key_schedule.encrypt(&state);
+    }
+    state[0]
+}
+
+pub fn rescue_mac_verify_native<
+    E: Engine,
+    const SIZE: usize,
+    const RNUMBER: usize>(
+    key_schedule: &NativeKeySchedule<E, SIZE, RNUMBER>,
+    message: &[E::Fr],
+    tag: &E::Fr)->bool{
+
+    rescue_mac_native(key_schedule, message) == *tag
+}
+
+pub fn rescue_prf<
+    E: Engine,
+    CS: ConstraintSystem<E>,
+    const SIZE: usize,
+    const RNUMBER: usize>(
+    cs: &mut CS,
+    params: &ReadyCipherParams<E, SIZE, RNUMBER>,
+    key_schedule: &KeySchedule<E, SIZE, RNUMBER>,
+    input: &[Num<E>; SIZE])->Result<[Num<E>; SIZE], CipherError>{
+
+    let mut output = rescue_encryption_with_key_schedule(cs, params, key_schedule, input)?;
+    for (o, i) in output.iter_mut().zip(input.iter()) {
+        *o = o.add(cs, i)?;
+    }
+    Ok(output)
+}
+
+pub fn rescue_mac<
+    E: Engine,
+    CS: ConstraintSystem<E>,
+    const SIZE: usize,
+    const RNUMBER: usize>(
+    cs: &mut CS,
+    params: &ReadyCipherParams<E, SIZE, RNUMBER>,
+    key_schedule: &KeySchedule<E, SIZE, RNUMBER>,
+    message: &[Num<E>])->Result<Num<E>, CipherError>{
+
+    let mut state = [Num::<E>::zero(); SIZE];
+    state[0] = Num::Constant(u64_to_fe::<E>(message.len() as u64));
+    state = rescue_encryption_with_key_schedule(cs, params, key_schedule, &state)?;
+
+    for chunk in message.chunks(SIZE) {
+        for (s, m) in state.iter_mut().zip(chunk.iter()) {
+            *s = s.add(cs, m)?;
+        }
+        state = rescue_encryption_with_key_schedule(cs, params, key_schedule, &state)?;
+    }
+    Ok(state[0])
+}
+
+// Returns whether the tag is valid instead of enforcing it, and the cost is
+// the same for valid and invalid tags, so the result can stay private.
+pub fn rescue_mac_verify<
+    E: Engine,
+    CS: ConstraintSystem<E>,
+    const SIZE: usize,
+    const RNUMBER: usize>(
+    cs: &mut CS,
+    params: &ReadyCipherParams<E, SIZE, RNUMBER>,
+    key_schedule: &KeySchedule<E, SIZE, RNUMBER>,
+    message: &[Num<E>],
+    tag: &Num<E>)->Result<Boolean, CipherError>{
+
+    let expected = rescue_mac(cs, params, key_schedule, message)?;
+    Ok(Num::equals(cs, &expected, tag)?)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::cipher_tools::generate_cipher_params;
+    use crate::enc_dec::construct_ready_params;
+    use crate::tests::{init_cs, init_rng, test_inputs};
+    use franklin_crypto::bellman::pairing::bn256::{Bn256, Fr};
+    use rand::Rand;
+
+    const SIZE: usize = 3;
+    const RNUMBER: usize = 7;
+
+    #[test]
+    fn test_mac_native() {
+        let rng = &mut init_rng();
+        let params = generate_cipher_params::<Bn256, _, SIZE, RNUMBER>(rng);
+        let key_schedule = NativeKeySchedule::new(&params, &test_inputs::<Bn256, SIZE>(rng)).unwrap();
+        let other_key_schedule = NativeKeySchedule::new(&params, &test_inputs::<Bn256, SIZE>(rng)).unwrap();
+        let message: Vec<Fr> = (0..(2 * SIZE + 1)).map(|_| Fr::rand(rng)).collect();
+
+        let tag = rescue_mac_native(&key_schedule, &message);
+        assert!(rescue_mac_verify_native(&key_schedule, &message, &tag));
+        assert!(!rescue_mac_verify_native(&other_key_schedule, &message, &tag));
+
+        let mut tampered = message.clone();
+        tampered[SIZE].add_assign(&Fr::one());
+        assert!(!rescue_mac_verify_native(&key_schedule, &tampered, &tag));
+
+        // zero padding of the last block does not collide with a longer message
+        let mut padded = message.clone();
+        padded.push(Fr::zero());
+        assert_ne!(rescue_mac_native(&key_schedule, &padded), tag);
+        assert_ne!(rescue_mac_native(&key_schedule, &[]), rescue_mac_native(&key_schedule, &[Fr::zero()]));
+    }
+
+    fn run_circuit_mac(tamper_tag: bool) -> (bool, usize) {
+        let rng = &mut init_rng();
+        let cs = &mut init_cs::<Bn256>();
+        let params = generate_cipher_params::<Bn256, _, SIZE, RNUMBER>(rng);
+        let key = test_inputs::<Bn256, SIZE>(rng);
+        let input = test_inputs::<Bn256, SIZE>(rng);
+        let message: Vec<Fr> = (0..(SIZE + 2)).map(|_| Fr::rand(rng)).collect();
+
+        let native_key_schedule = NativeKeySchedule::new(&params, &key).unwrap();
+        let expected_output = rescue_prf_native(&native_key_schedule, &input);
+        let mut expected_tag = rescue_mac_native(&native_key_schedule, &message);
+
+        let mut key_as_nums = [Num::<Bn256>::zero(); SIZE];
+        let mut input_as_nums = [Num::<Bn256>::zero(); SIZE];
+        for i in 0..SIZE {
+            key_as_nums[i] = Num::alloc(cs, Some(key[i])).unwrap();
+            input_as_nums[i] = Num::alloc(cs, Some(input[i])).unwrap();
+        }
+        let message_as_nums: Vec<Num<Bn256>> = message.iter().map(|m| Num::alloc(cs, Some(*m)).unwrap()).collect();
+        let ready_params = construct_ready_params(&params).unwrap();
+        let key_schedule = KeySchedule::new(cs, &ready_params, &key_as_nums).unwrap();
+
+        let output = rescue_prf(cs, &ready_params, &key_schedule, &input_as_nums).unwrap();
+        for i in 0..SIZE {
+            assert_eq!(output[i].get_value().unwrap(), expected_output[i]);
+        }
+        let tag = rescue_mac(cs, &ready_params, &key_schedule, &message_as_nums).unwrap();
+        assert_eq!(tag.get_value().unwrap(), expected_tag);
+
+        if tamper_tag {
+            expected_tag.add_assign(&Fr::one());
+        }
+        let tag_as_num = Num::alloc(cs, Some(expected_tag)).unwrap();
+        let valid = rescue_mac_verify(cs, &ready_params, &key_schedule, &message_as_nums, &tag_as_num).unwrap();
+
+        let gates = cs.n();
+        cs.finalize();
+        assert!(cs.is_satisfied());
+        (valid.get_value().unwrap(), gates)
+    }
+
+    #[test]
+    fn test_mac_native_matches_circuit() {
+        let (valid, gates) = run_circuit_mac(false);
+        let (tampered_valid, tampered_gates) = run_circuit_mac(true);
+        assert!(valid);
+        assert!(!tampered_valid);
+        assert_eq!(gates, tampered_gates);
+    }
+}
@@ -1,30 +1,505 @@
-extern crate bellman;
-
-use rand::{ThreadRng, thread_rng};
 use rescue_block_cipher::{
-	cipher_tools::generate_cipher_params,
-	enc_dec::{rescue_decryption, rescue_encryption, construct_ready_params}
+	cipher_tools::{
+		CipherParams, generate_cipher_params, generate_cipher_params_from_tag,
+		defaults::{BN256_SIZE_2_TAG, BN256_SIZE_3_TAG, BN256_SIZE_4_TAG, BN256_SIZE_5_TAG},
+		serialization::{is_json_encoding, params_dimensions}
+	},
+	ctr::{ctr_encryption_native, ctr_decryption_native},
+	cost::{cipher_cost_report, cipher_cost_report_with_custom_gates},
+	enc_dec::{construct_ready_params, rescue_encryption, rescue_decryption},
+	error::CipherError,
+	native::{rescue_encryption_native, rescue_decryption_native, NativeKeySchedule}
 };
 use franklin_crypto::plonk::circuit::allocated_num::Num;
 use franklin_crypto::bellman::{
-	pairing::bn256::Bn256,
+	pairing::bn256::{Bn256, Fr},
+	pairing::ff::{Field, PrimeField, PrimeFieldRepr},
 	plonk::better_better_cs::cs::*
 };
+use num_bigint::BigUint;
+use rand::{OsRng, Rng};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+
+type CliResult<T> = Result<T, Box<dyn Error>>;
+
+const USAGE: &str = "usage: rescue_block_cipher <command> [options]
+
+commands:
+	paramgen       --out FILE [--tag TAG] [--json]   generate a parameter file, random unless a tag is given,
+	                                                 with --rounds subkeys
+	keygen         --out FILE                        generate a random key
+	encrypt        --params FILE --key FILE --in FILE --out FILE [--format hex|dec]
+	decrypt        --params FILE --key FILE --in FILE --out FILE [--format hex|dec]
+	circuit-stats  [--params FILE]                   print gate counts of the gadgets
+	selftest       [--params FILE]                   check that native and circuit versions agree
+
+common options:
+	--size N       state size, one of 2, 3, 4, 5 (default 3)
+	--rounds N     number of subkeys, see below (default: the default for the size)
+
+Files of field elements hold one element per line, either decimal or hex
+with a 0x prefix. Encryption uses counter mode with a random nonce that is
+stored as the first element of the ciphertext file. Without --params the
+default parameters for the state size are used.
+
+With --params the state size and the number of subkeys are read from the
+parameter file. Every state size supports its default number of subkeys and
+4 or 8 more:
+	size 2: 41, 45, 49
+	size 3: 29, 33, 37
+	size 4: 23, 27, 31
+	size 5: 19, 23, 27";
+
+const FLAGS: &[&str] = &["--json"];
+
+// Pairs of state size and number of subkeys the commands are instantiated
+// for, keep in sync with USAGE. The first of every size is the default one.
+macro_rules! dispatch {
+	($dimensions:expr, $command:ident, $options:expr) => {
+		match $dimensions {
+			(2, 41) => $command::<2, 41>($options),
+			(2, 45) => $command::<2, 45>($options),
+			(2, 49) => $command::<2, 49>($options),
+			(3, 29) => $command::<3, 29>($options),
+			(3, 33) => $command::<3, 33>($options),
+			(3, 37) => $command::<3, 37>($options),
+			(4, 23) => $command::<4, 23>($options),
+			(4, 27) => $command::<4, 27>($options),
+			(4, 31) => $command::<4, 31>($options),
+			(5, 19) => $command::<5, 19>($options),
+			(5, 23) => $command::<5, 23>($options),
+			(5, 27) => $command::<5, 27>($options),
+			(size, rounds) => Err(format!(
+				"state size {} with {} subkeys is not supported, see --help", size, rounds
+			).into())
+		}
+	};
+}
 
 fn main() {
-	let rng = &mut thread_rng();
+	let args: Vec<String> = std::env::args().skip(1).collect();
+	if let Err(err) = run(&args) {
+		eprintln!("error: {}", err);
+		std::process::exit(1);
+	}
+}
+
+fn run(args: &[String]) -> CliResult<()> {
+	let command = match args.first() {
+		Some(command) => command.as_str(),
+		None => {
+			println!("{}", USAGE);
+			return Ok(());
+		}
+	};
+	let options = Options::parse(&args[1..])?;
+	let dimensions = options.dimensions()?;
+
+	match command {
+		"paramgen" => dispatch!(dimensions, paramgen, &options),
+		"keygen" => dispatch!(dimensions, keygen, &options),
+		"encrypt" => dispatch!(dimensions, encrypt, &options),
+		"decrypt" => dispatch!(dimensions, decrypt, &options),
+		"circuit-stats" => dispatch!(dimensions, circuit_stats, &options),
+		"selftest" => dispatch!(dimensions, selftest, &options),
+		"help" | "--help" | "-h" => {
+			println!("{}", USAGE);
+			Ok(())
+		}
+		command => Err(format!("unknown command {}\n\n{}", command, USAGE).into())
+	}
+}
+
+struct Options {
+	values: HashMap<String, String>,
+	flags: Vec<String>
+}
+
+impl Options {
+	fn parse(args: &[String]) -> CliResult<Self> {
+		let mut values = HashMap::new();
+		let mut flags = vec![];
+		let mut iter = args.iter();
+		while let Some(arg) = iter.next() {
+			if !arg.starts_with("--") {
+				return Err(format!("unexpected argument {}", arg).into());
+			}
+			if FLAGS.contains(&arg.as_str()) {
+				flags.push(arg.clone());
+				continue;
+			}
+			let value = iter.next().ok_or_else(|| format!("missing value for {}", arg))?;
+			values.insert(arg[2..].to_string(), value.clone());
+		}
+		Ok(Options { values, flags })
+	}
+
+	fn get(&self, name: &str) -> Option<&str> {
+		self.values.get(name).map(|value| value.as_str())
+	}
+
+	fn required(&self, name: &str) -> CliResult<&str> {
+		self.get(name).ok_or_else(|| format!("missing option --{}", name).into())
+	}
+
+	fn flag(&self, name: &str) -> bool {
+		self.flags.iter().any(|flag| flag == name)
+	}
+
+	fn size(&self) -> CliResult<usize> {
+		match self.get("size") {
+			Some(size) => Ok(size.parse()?),
+			None => Ok(3)
+		}
+	}
+
+	fn rounds(&self, size: usize) -> CliResult<usize> {
+		match self.get("rounds") {
+			Some(rounds) => Ok(rounds.parse()?),
+			None => default_rounds(size)
+		}
+	}
+
+	// State size and number of subkeys, taken from the parameter file if
+	// there is one. Explicit --size or --rounds must agree with the file.
+	fn dimensions(&self) -> CliResult<(usize, usize)> {
+		let path = match self.get("params") {
+			Some(path) => path,
+			None => {
+				let size = self.size()?;
+				return Ok((size, self.rounds(size)?));
+			}
+		};
+		let (size, rounds) = params_dimensions(&fs::read(path)?)?;
+		if self.get("size").is_some() && self.size()? != size {
+			return Err(format!("--size does not match the state size {} of {}", size, path).into());
+		}
+		if self.get("rounds").is_some() && self.rounds(size)? != rounds {
+			return Err(format!("--rounds does not match the {} subkeys of {}", rounds, path).into());
+		}
+		Ok((size, rounds))
+	}
+
+	fn hex_output(&self) -> CliResult<bool> {
+		match self.get("format") {
+			None | Some("hex") => Ok(true),
+			Some("dec") => Ok(false),
+			Some(format) => Err(format!("unknown format {}", format).into())
+		}
+	}
+}
+
+fn paramgen<const SIZE: usize, const RNUMBER: usize>(options: &Options) -> CliResult<()> {
+	let out = options.required("out")?;
+	let params: CipherParams<Bn256, SIZE, RNUMBER> = match options.get("tag") {
+		Some(tag) => generate_cipher_params_from_tag(tag.as_bytes())?,
+		None => generate_cipher_params(&mut OsRng::new()?)
+	};
+
+	if options.flag("--json") {
+		fs::write(out, params.to_json())?;
+	} else {
+		fs::write(out, params.to_bytes())?;
+	}
+	Ok(())
+}
+
+fn keygen<const SIZE: usize, const RNUMBER: usize>(options: &Options) -> CliResult<()> {
+	let out = options.required("out")?;
+	let rng = &mut OsRng::new()?;
+	let key: Vec<Fr> = (0..SIZE).map(|_| rng.gen()).collect();
+	write_elements(out, &key, true)
+}
+
+fn encrypt<const SIZE: usize, const RNUMBER: usize>(options: &Options) -> CliResult<()> {
+	let params = load_params::<SIZE, RNUMBER>(options)?;
+	let key_schedule = NativeKeySchedule::new(&params, &load_key::<SIZE>(options)?)?;
+	let message = read_elements(options.required("in")?)?;
+
+	let nonce: Fr = OsRng::new()?.gen();
+	let mut output = vec![nonce];
+	output.extend(ctr_encryption_native(&key_schedule, &nonce, &message)?);
+	write_elements(options.required("out")?, &output, options.hex_output()?)
+}
+
+fn decrypt<const SIZE: usize, const RNUMBER: usize>(options: &Options) -> CliResult<()> {
+	let params = load_params::<SIZE, RNUMBER>(options)?;
+	let key_schedule = NativeKeySchedule::new(&params, &load_key::<SIZE>(options)?)?;
+	let input = read_elements(options.required("in")?)?;
+
+	let (nonce, ciphertext) = input.split_first().ok_or("ciphertext file has no nonce")?;
+	let message = ctr_decryption_native(&key_schedule, nonce, ciphertext)?;
+	write_elements(options.required("out")?, &message, options.hex_output()?)
+}
+
+fn circuit_stats<const SIZE: usize, const RNUMBER: usize>(options: &Options) -> CliResult<()> {
+	let params = load_params::<SIZE, RNUMBER>(options)?;
+
+	let report = cipher_cost_report(&params)?;
+	let custom_report = cipher_cost_report_with_custom_gates(&params)?;
+
+	println!("state size {}, {} subkeys", SIZE, RNUMBER);
+	println!("{:<28}{:>12}{:>16}", "component", "main gate", "custom gates");
+	for ((component, gates), (_, custom_gates)) in report.components().iter().zip(custom_report.components().iter()) {
+		println!("{:<28}{:>12}{:>16}", component, gates, custom_gates);
+	}
+	Ok(())
+}
+
+fn selftest<const SIZE: usize, const RNUMBER: usize>(options: &Options) -> CliResult<()> {
+	let params = load_params::<SIZE, RNUMBER>(options)?;
+	let rng = &mut OsRng::new()?;
+	let key: [Fr; SIZE] = random_block(rng);
+	let plaintext: [Fr; SIZE] = random_block(rng);
+
+	let expected_ciphertext = rescue_encryption_native(&params, &key, &plaintext)?;
+	if rescue_decryption_native(&params, &key, &expected_ciphertext)? != plaintext {
+		return Err("native decryption does not invert encryption".into());
+	}
+
 	let mut cs = TrivialAssembly::<Bn256, PlonkCsWidth4WithNextStepParams, Width4MainGateWithDNext>::new();
+	let key_as_nums = alloc_block(&mut cs, &key)?;
+	let plaintext_as_nums = alloc_block(&mut cs, &plaintext)?;
+	let ready_params = construct_ready_params(&params)?;
+	let ciphertext = rescue_encryption(&mut cs, &ready_params, &key_as_nums, &plaintext_as_nums)?;
+	let decrypted = rescue_decryption(&mut cs, &ready_params, &key_as_nums, &ciphertext)?;
+
+	for i in 0..SIZE {
+		if ciphertext[i].get_value() != Some(expected_ciphertext[i]) {
+			return Err("circuit encryption does not match native encryption".into());
+		}
+		if decrypted[i].get_value() != Some(plaintext[i]) {
+			return Err("circuit decryption does not match native decryption".into());
+		}
+	}
+	cs.finalize();
+	if !cs.is_satisfied() {
+		return Err("encryption circuit is not satisfied".into());
+	}
+
+	println!("selftest passed");
+	Ok(())
+}
+
+fn load_params<const SIZE: usize, const RNUMBER: usize>(options: &Options) -> CliResult<CipherParams<Bn256, SIZE, RNUMBER>> {
+	let path = match options.get("params") {
+		Some(path) => path,
+		None if RNUMBER == default_rounds(SIZE)? => {
+			return Ok(generate_cipher_params_from_tag(default_tag(SIZE)?)?);
+		}
+		None => return Err(format!("parameters with {} subkeys need --params", RNUMBER).into())
+	};
+	let bytes = fs::read(path)?;
+	let params = if is_json_encoding(&bytes) {
+		CipherParams::from_json(std::str::from_utf8(&bytes)?)?
+	} else {
+		CipherParams::from_bytes(&bytes)?
+	};
+	Ok(params)
+}
+
+fn default_tag(size: usize) -> CliResult<&'static [u8]> {
+	match size {
+		2 => Ok(BN256_SIZE_2_TAG),
+		3 => Ok(BN256_SIZE_3_TAG),
+		4 => Ok(BN256_SIZE_4_TAG),
+		5 => Ok(BN256_SIZE_5_TAG),
+		size => Err(CipherError::UnsupportedSize(size).into())
+	}
+}
+
+fn default_rounds(size: usize) -> CliResult<usize> {
+	match size {
+		2 => Ok(41),
+		3 => Ok(29),
+		4 => Ok(23),
+		5 => Ok(19),
+		size => Err(CipherError::UnsupportedSize(size).into())
+	}
+}
+
+fn load_key<const SIZE: usize>(options: &Options) -> CliResult<[Fr; SIZE]> {
+	let elements = read_elements(options.required("key")?)?;
+	if elements.len() != SIZE {
+		return Err(format!("key must have {} elements, found {}", SIZE, elements.len()).into());
+	}
+	let mut key = [Fr::zero(); SIZE];
+	key.copy_from_slice(&elements);
+	Ok(key)
+}
+
+fn read_elements(path: &str) -> CliResult<Vec<Fr>> {
+	let content = fs::read_to_string(path)?;
+	content.lines()
+		.map(|line| line.trim())
+		.filter(|line| !line.is_empty() && !line.starts_with('#'))
+		.map(parse_element)
+		.collect()
+}
+
+fn write_elements(path: &str, elements: &[Fr], hex_output: bool) -> CliResult<()> {
+	let mut content = String::new();
+	for element in elements.iter() {
+		let bytes = element_to_bytes(element);
+		if hex_output {
+			content.push_str(&format!("0x{}\n", hex::encode(bytes)));
+		} else {
+			content.push_str(&format!("{}\n", BigUint::from_bytes_be(&bytes)));
+		}
+	}
+	fs::write(path, content)?;
+	Ok(())
+}
+
+// Elements must be canonical, values at or above the modulus are rejected
+// instead of being silently reduced.
+fn parse_element(text: &str) -> CliResult<Fr> {
+	let value = match text.strip_prefix("0x") {
+		Some(digits) => BigUint::parse_bytes(digits.as_bytes(), 16),
+		None => BigUint::parse_bytes(text.as_bytes(), 10)
+	}.ok_or_else(|| format!("invalid field element {}", text))?;
+
+	let element_length = element_to_bytes(&Fr::zero()).len();
+	let bytes = value.to_bytes_be();
+	if bytes.len() > element_length {
+		return Err(format!("field element {} is out of range", text).into());
+	}
+	let mut padded = vec![0u8; element_length - bytes.len()];
+	padded.extend_from_slice(&bytes);
+
+	let mut repr = <Fr as PrimeField>::Repr::default();
+	repr.read_be(&padded[..])?;
+	Fr::from_repr(repr).map_err(|_| format!("field element {} is out of range", text).into())
+}
+
+fn element_to_bytes(element: &Fr) -> Vec<u8> {
+	let mut bytes = vec![];
+	element.into_repr().write_be(&mut bytes).expect("writing into a vector never fails");
+	bytes
+}
+
+fn random_block<R: Rng, const SIZE: usize>(rng: &mut R) -> [Fr; SIZE] {
+	let mut block = [Fr::zero(); SIZE];
+	for element in block.iter_mut() {
+		*element = rng.gen();
+	}
+	block
+}
+
+fn alloc_block<CS: ConstraintSystem<Bn256>, const SIZE: usize>(cs: &mut CS, block: &[Fr; SIZE]) -> CliResult<[Num<Bn256>; SIZE]> {
+	let mut nums = [Num::<Bn256>::zero(); SIZE];
+	for (num, element) in nums.iter_mut().zip(block.iter()) {
+		*num = Num::alloc(cs, Some(*element))?;
+	}
+	Ok(nums)
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use std::path::PathBuf;
+
+	fn args(items: &[&str]) -> Vec<String> {
+		items.iter().map(|item| item.to_string()).collect()
+	}
+
+	fn temp_path(name: &str) -> String {
+		let dir: PathBuf = std::env::temp_dir().join(format!("rescue_block_cipher_cli_{}", std::process::id()));
+		fs::create_dir_all(&dir).unwrap();
+		dir.join(name).to_str().unwrap().to_string()
+	}
+
+	fn modulus() -> BigUint {
+		let mut bytes = vec![];
+		Fr::char().write_be(&mut bytes).unwrap();
+		BigUint::from_bytes_be(&bytes)
+	}
+
+	#[test]
+	fn test_parse_element() {
+		let expected = Fr::from_str("42").unwrap();
+		assert_eq!(parse_element("42").unwrap(), expected);
+		assert_eq!(parse_element("0x2a").unwrap(), expected);
+		assert_eq!(parse_element("0x000000000000002A").unwrap(), expected);
+
+		let mut minus_one = Fr::zero();
+		minus_one.sub_assign(&Fr::one());
+		assert_eq!(parse_element(&(modulus() - 1u32).to_string()).unwrap(), minus_one);
+		assert!(parse_element(&modulus().to_string()).is_err());
+		assert!(parse_element(&format!("0x{}", "ff".repeat(40))).is_err());
+
+		for text in ["", "0x", "-1", "4x2", "0xzz"].iter() {
+			assert!(parse_element(text).is_err(), "{} was accepted", text);
+		}
+	}
+
+	#[test]
+	fn test_options() {
+		let options = Options::parse(&args(&["--in", "message", "--json", "--size", "4", "--format", "dec"])).unwrap();
+		assert_eq!(options.get("in"), Some("message"));
+		assert_eq!(options.required("in").unwrap(), "message");
+		assert!(options.required("out").is_err());
+		assert!(options.flag("--json"));
+		assert_eq!(options.size().unwrap(), 4);
+		assert_eq!(options.dimensions().unwrap(), (4, 23));
+		assert!(!options.hex_output().unwrap());
+
+		let options = Options::parse(&args(&[])).unwrap();
+		assert!(!options.flag("--json"));
+		assert_eq!(options.dimensions().unwrap(), (3, 29));
+		assert!(options.hex_output().unwrap());
+
+		let options = Options::parse(&args(&["--rounds", "33"])).unwrap();
+		assert_eq!(options.dimensions().unwrap(), (3, 33));
+
+		assert!(Options::parse(&args(&["--out"])).is_err());
+		assert!(Options::parse(&args(&["out", "file"])).is_err());
+		assert!(Options::parse(&args(&["--format", "oct"])).unwrap().hex_output().is_err());
+		assert!(Options::parse(&args(&["--size", "three"])).unwrap().size().is_err());
+	}
+
+	#[test]
+	fn test_encrypt_decrypt_round_trip() {
+		let key = temp_path("round_trip.key");
+		let message = temp_path("round_trip.msg");
+		let ciphertext = temp_path("round_trip.enc");
+		let decrypted = temp_path("round_trip.dec");
+		fs::write(&message, "# message\n1\n0x2a\n123456789\n").unwrap();
+
+		run(&args(&["keygen", "--out", &key])).unwrap();
+		run(&args(&["encrypt", "--key", &key, "--in", &message, "--out", &ciphertext])).unwrap();
+		run(&args(&["decrypt", "--key", &key, "--in", &ciphertext, "--out", &decrypted, "--format", "dec"])).unwrap();
+
+		let message = read_elements(&message).unwrap();
+		assert_eq!(read_elements(&ciphertext).unwrap().len(), message.len() + 1);
+		assert_eq!(read_elements(&decrypted).unwrap(), message);
+	}
 
-	let mut my_params = generate_cipher_params::<Bn256, ThreadRng, 3, 7>(rng);
-	let ready_params = construct_ready_params(&mut cs, &mut my_params);
+	#[test]
+	fn test_round_trip_with_non_default_rounds() {
+		let params = temp_path("rounds.params");
+		let key = temp_path("rounds.key");
+		let message = temp_path("rounds.msg");
+		let ciphertext = temp_path("rounds.enc");
+		let decrypted = temp_path("rounds.dec");
+		fs::write(&message, "7\n8\n9\n").unwrap();
 
-	let key = [Num::<Bn256>::zero(); 3];
-	let plaintext = [Num::<Bn256>::zero(); 3];
+		run(&args(&["paramgen", "--size", "2", "--rounds", "45", "--tag", "cli test", "--out", &params])).unwrap();
+		let options = Options::parse(&args(&["--params", &params])).unwrap();
+		assert_eq!(options.dimensions().unwrap(), (2, 45));
+		assert!(Options::parse(&args(&["--params", &params, "--size", "3"])).unwrap().dimensions().is_err());
+		assert!(Options::parse(&args(&["--params", &params, "--rounds", "41"])).unwrap().dimensions().is_err());
 
-	let ciphertext = rescue_encryption(&mut cs, &ready_params, &key, &plaintext);
-	let decrypted_plaintext = rescue_decryption(&mut cs, &ready_params, &key, &ciphertext);
+		run(&args(&["keygen", "--size", "2", "--out", &key])).unwrap();
+		run(&args(&["encrypt", "--params", &params, "--key", &key, "--in", &message, "--out", &ciphertext])).unwrap();
+		run(&args(&["decrypt", "--params", &params, "--key", &key, "--in", &ciphertext, "--out", &decrypted])).unwrap();
+		assert_eq!(read_elements(&decrypted).unwrap(), read_elements(&message).unwrap());
 
-	for i in 0..3 {
-		plaintext[i].enforce_equal(&mut cs, &decrypted_plaintext[i]).unwrap();
+		// the key has two elements, the default parameters of size 3 need three
+		assert!(run(&args(&["encrypt", "--key", &key, "--in", &message, "--out", &ciphertext])).is_err());
+		assert!(run(&args(&["encrypt", "--rounds", "45", "--size", "2", "--key", &key, "--in", &message, "--out", &ciphertext])).is_err());
+		assert!(run(&args(&["paramgen", "--size", "3", "--rounds", "30", "--out", &params])).is_err());
 	}
 }
@@ -0,0 +1,240 @@
+use franklin_crypto::bellman::pairing::Engine;
+use franklin_crypto::bellman::pairing::ff::Field;
+use super::cipher_tools::{
+    CipherParams,
+    sboxes::{AlphaSBox, AlphaInverseSBox},
+    mds::{
+        construct_mds_matrix_native, construct_inverse_matrix_native,
+        matrix_vector_product_native, add_vectors_native, sub_vectors_native
+    }
+};
+use super::error::CipherError;
+
+// The parameters are checked and both matrices are built once here. The
+// schedule keeps everything the rounds need, so it encrypts and decrypts
+// without the parameters it was built from.
+pub struct NativeKeySchedule<
+    E: Engine,
+    const SIZE: usize,
+    const RNUMBER: usize> {
+    pub subkeys: [[E::Fr; SIZE]; RNUMBER],
+    pub(crate) matrix: [[E::Fr; SIZE]; SIZE],
+    pub(crate) inv_matrix: [[E::Fr; SIZE]; SIZE],
+    sbox1: AlphaSBox<E, SIZE>,
+    sbox2: AlphaInverseSBox<E, SIZE>,
+    round_constants: [[E::Fr; SIZE]; RNUMBER]
+}
+
+impl<E: Engine, const SIZE: usize, const RNUMBER: usize> NativeKeySchedule<E, SIZE, RNUMBER> {
+    pub fn new(
+        params: &CipherParams<E, SIZE, RNUMBER>,
+        key: &[E::Fr; SIZE])->Result<Self, CipherError>{
+        params.check()?;
+        let matrix = construct_mds_matrix_native::<E, SIZE>(&params.vect_for_matrix)?;
+        let inv_matrix = construct_inverse_matrix_native::<E, SIZE>(&matrix)?;
+        let subkeys = construct_subkeys_native(params, &matrix, key);
+        Ok(NativeKeySchedule {
+            subkeys,
+            matrix,
+            inv_matrix,
+            sbox1: params.sbox1.clone(),
+            sbox2: params.sbox2.clone(),
+            round_constants: params.round_constants
+        })
+    }
+
+    // Key schedule of the tweakable cipher, see `tweak_subkeys_native`. The
+    // untweaked schedule can be computed once and tweaked per record.
+    pub fn tweaked(&self, tweak: &E::Fr)->Self{
+        NativeKeySchedule {
+            subkeys: tweak_subkeys_with_constants::<E, SIZE, RNUMBER>(&self.round_constants, &self.subkeys, tweak),
+            matrix: self.matrix,
+            inv_matrix: self.inv_matrix,
+            sbox1: self.sbox1.clone(),
+            sbox2: self.sbox2.clone(),
+            round_constants: self.round_constants
+        }
+    }
+
+    pub fn encrypt(&self, plaintext: &[E::Fr; SIZE])->[E::Fr; SIZE]{
+        let mut ciphertext = add_vectors_native::<E, SIZE>(plaintext, &self.subkeys[0]);
+        for i in 1..RNUMBER {
+            ciphertext = self.encryption_round(&ciphertext, i);
+        }
+        ciphertext
+    }
+
+    pub fn decrypt(&self, ciphertext: &[E::Fr; SIZE])->[E::Fr; SIZE]{
+        let mut plaintext = sub_vectors_native::<E, SIZE>(ciphertext, &self.subkeys[RNUMBER-1]);
+
+        for i in 1..RNUMBER {
+            // undo the S-box of encryption round RNUMBER-i
+            if (RNUMBER-i)%2 == 1 {
+                self.sbox2.apply_native(&mut plaintext);
+            } else {
+                self.sbox1.apply_native(&mut plaintext);
+            }
+            plaintext = matrix_vector_product_native::<E, SIZE>(&self.inv_matrix, &plaintext);
+            plaintext = sub_vectors_native::<E, SIZE>(&plaintext, &self.subkeys[RNUMBER-i-1]);
+        }
+        plaintext
+    }
+
+    // Native counterpart of `enc_dec::encryption_round`.
+    fn encryption_round(&self, state: &[E::Fr; SIZE], round: usize)->[E::Fr; SIZE]{
+        let mut state = matrix_vector_product_native::<E, SIZE>(&self.matrix, state);
+        if round%2 == 1 {
+            self.sbox1.apply_native(&mut state);
+        } else {
+            self.sbox2.apply_native(&mut state);
+        }
+        add_vectors_native::<E, SIZE>(&state, &self.subkeys[round])
+    }
+}
+
+pub fn rescue_encryption_native<
+    E: Engine,
+    const SIZE: usize,
+    const RNUMBER: usize>(
+    params: &CipherParams<E, SIZE, RNUMBER>,
+    key: &[E::Fr; SIZE],
+    plaintext: &[E::Fr; SIZE])->Result<[E::Fr; SIZE], CipherError>{
+
+    let key_schedule = NativeKeySchedule::new(params, key)?;
+    Ok(rescue_encryption_with_key_schedule_native(&key_schedule, plaintext))
+}
+
+pub fn rescue_decryption_native<
+    E: Engine,
+    const SIZE: usize,
+    const RNUMBER: usize>(
+    params: &CipherParams<E, SIZE, RNUMBER>,
+    key: &[E::Fr; SIZE],
+    ciphertext: &[E::Fr; SIZE])->Result<[E::Fr; SIZE], CipherError>{
+
+    let key_schedule = NativeKeySchedule::new(params, key)?;
+    Ok(rescue_decryption_with_key_schedule_native(&key_schedule, ciphertext))
+}
+
+pub fn rescue_tweakable_encryption_native<
+    E: Engine,
+    const SIZE: usize,
+    const RNUMBER: usize>(
+    params: &CipherParams<E, SIZE, RNUMBER>,
+    key: &[E::Fr; SIZE],
+    tweak: &E::Fr,
+    plaintext: &[E::Fr; SIZE])->Result<[E::Fr; SIZE], CipherError>{
+
+    let key_schedule = NativeKeySchedule::new(params, key)?.tweaked(tweak);
+    Ok(rescue_encryption_with_key_schedule_native(&key_schedule, plaintext))
+}
+
+pub fn rescue_tweakable_decryption_native<
+    E: Engine,
+    const SIZE: usize,
+    const RNUMBER: usize>(
+    params: &CipherParams<E, SIZE, RNUMBER>,
+    key: &[E::Fr; SIZE],
+    tweak: &E::Fr,
+    ciphertext: &[E::Fr; SIZE])->Result<[E::Fr; SIZE], CipherError>{
+
+    let key_schedule = NativeKeySchedule::new(params, key)?.tweaked(tweak);
+    Ok(rescue_decryption_with_key_schedule_native(&key_schedule, ciphertext))
+}
+
+pub fn rescue_encryption_with_key_schedule_native<
+    E: Engine,
+    const SIZE: usize,
+    const RNUMBER: usize>(
+    key_schedule: &NativeKeySchedule<E, SIZE, RNUMBER>,
+    plaintext: &[E::Fr; SIZE])->[E::Fr; SIZE]{
+
+    key_schedule.encrypt(plaintext)
+}
+
+pub fn rescue_decryption_with_key_schedule_native<
+    E: Engine,
+    const SIZE: usize,
+    const RNUMBER: usize>(
+    key_schedule: &NativeKeySchedule<E, SIZE, RNUMBER>,
+    ciphertext: &[E::Fr; SIZE])->[E::Fr; SIZE]{
+
+    key_schedule.decrypt(ciphertext)
+}
+
+// States of the encryption after every subkey addition: the first one is the
+// whitened plaintext, the last one the ciphertext. Meant for checking
+// implementations against known intermediate values.
+pub fn rescue_encryption_trace_native<
+    E: Engine,
+    const SIZE: usize,
+    const RNUMBER: usize>(
+    key_schedule: &NativeKeySchedule<E, SIZE, RNUMBER>,
+    plaintext: &[E::Fr; SIZE])->[[E::Fr; SIZE]; RNUMBER]{
+
+    let mut states = [[E::Fr::zero(); SIZE]; RNUMBER];
+    states[0] = add_vectors_native::<E, SIZE>(plaintext, &key_schedule.subkeys[0]);
+    for i in 1..RNUMBER {
+        states[i] = key_schedule.encryption_round(&states[i-1], i);
+    }
+    states
+}
+
+pub fn construct_subkeys_native<
+    E: Engine,
+    const SIZE: usize,
+    const RNUMBER: usize>(
+    params: &CipherParams<E, SIZE, RNUMBER>,
+    matrix: &[[E::Fr; SIZE]; SIZE],
+    key: &[E::Fr; SIZE])->[[E::Fr; SIZE]; RNUMBER]{
+
+    let mut subkeys = [*key; RNUMBER];
+    let raconsts = &params.round_constants;
+
+    subkeys[0] = add_vectors_native::<E, SIZE>(key, &raconsts[0]);
+
+    for i in 1..RNUMBER {
+        subkeys[i] = matrix_vector_product_native::<E, SIZE>(matrix, &subkeys[i-1]);
+        if i%2 == 1 {
+            params.sbox1.apply_native(&mut subkeys[i]);
+        } else {
+            params.sbox2.apply_native(&mut subkeys[i]);
+        }
+        subkeys[i] = add_vectors_native::<E, SIZE>(&subkeys[i], &raconsts[i]);
+    }
+    subkeys
+}
+
+// Adds tweak * round_constants[i] to every subkey but the whitening one.
+// Scaling by the round constants makes the injection differ from round to
+// round, and since the whitening key is left alone a tweak difference can not
+// cancel a plaintext difference before the first S-box.
+pub fn tweak_subkeys_native<
+    E: Engine,
+    const SIZE: usize,
+    const RNUMBER: usize>(
+    params: &CipherParams<E, SIZE, RNUMBER>,
+    subkeys: &[[E::Fr; SIZE]; RNUMBER],
+    tweak: &E::Fr)->[[E::Fr; SIZE]; RNUMBER]{
+
+    tweak_subkeys_with_constants::<E, SIZE, RNUMBER>(&params.round_constants, subkeys, tweak)
+}
+
+fn tweak_subkeys_with_constants<
+    E: Engine,
+    const SIZE: usize,
+    const RNUMBER: usize>(
+    round_constants: &[[E::Fr; SIZE]; RNUMBER],
+    subkeys: &[[E::Fr; SIZE]; RNUMBER],
+    tweak: &E::Fr)->[[E::Fr; SIZE]; RNUMBER]{
+
+    let mut tweaked = *subkeys;
+    for i in 1..RNUMBER {
+        for j in 0..SIZE {
+            let mut injection = round_constants[i][j];
+            injection.mul_assign(tweak);
+            tweaked[i][j].add_assign(&injection);
+        }
+    }
+    tweaked
+}
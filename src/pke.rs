@@ -0,0 +1,354 @@
+use franklin_crypto::plonk::circuit::allocated_num::Num;
+use franklin_crypto::plonk::circuit::boolean::{AllocatedBit, Boolean};
+use franklin_crypto::plonk::circuit::linear_combination::LinearCombination;
+use franklin_crypto::plonk::circuit::twisted_edwards::edwards::{
+    CircuitTwistedEdwardsCurveImplementor, CircuitTwistedEdwardsPoint
+};
+use franklin_crypto::twisted_edwards::edwards::{
+    TwistedEdwardsCurveImplementor, TwistedEdwardsCurveParams, TwistedEdwardsPoint
+};
+use franklin_crypto::bellman::pairing::Engine;
+use franklin_crypto::bellman::pairing::ff::{Field, PrimeField};
+use franklin_crypto::bellman::plonk::better_better_cs::cs::ConstraintSystem;
+use rescue_poseidon::{circuit_generic_hash_num, generic_hash, RescueParams};
+use super::cipher_tools::{CipherParams, u64_to_fe};
+use super::enc_dec::{ReadyCipherParams, rescue_encryption};
+use super::native::{rescue_encryption_native, rescue_decryption_native};
+use super::error::CipherError;
+
+// Hybrid public-key encryption. The sender picks an ephemeral scalar r and
+// publishes R = r * G next to the ciphertext. The shared point S = r * PK
+// (equal to sk * R for the key holder) is hashed into the cipher key as
+// key[i] = rescue_hash(S.x, S.y, i)[0] and the message is encrypted with
+// the Rescue block cipher under that key. The point arithmetic is the
+// twisted Edwards implementation of franklin_crypto, natively and in the
+// circuit.
+pub fn public_key_native<E: Engine, C: TwistedEdwardsCurveParams<E>>(
+    curve: &TwistedEdwardsCurveImplementor<E, C>,
+    secret_key: &C::Fs)->TwistedEdwardsPoint<E>{
+
+    curve.mul_by_generator(secret_key.into_repr())
+}
+
+// Returns the ephemeral public key R and the ciphertext. Public keys outside
+// the prime order subgroup or equal to the identity are refused: a torsion
+// component would leak the ephemeral scalar modulo the cofactor and the
+// identity makes the shared point independent of the secret scalars.
+pub fn pke_encryption_native<
+    E: Engine,
+    C: TwistedEdwardsCurveParams<E>,
+    const SIZE: usize,
+    const RNUMBER: usize>(
+    params: &CipherParams<E, SIZE, RNUMBER>,
+    curve: &TwistedEdwardsCurveImplementor<E, C>,
+    public_key: &TwistedEdwardsPoint<E>,
+    ephemeral_key: &C::Fs,
+    message: &[E::Fr; SIZE])->Result<(TwistedEdwardsPoint<E>, [E::Fr; SIZE]), CipherError>{
+
+    check_point_native(curve, public_key)?;
+    let ephemeral_public_key = public_key_native(curve, ephemeral_key);
+    check_point_native(curve, &ephemeral_public_key)?;
+    let shared_secret = curve.mul(public_key, ephemeral_key.into_repr());
+    let key = derive_key_native::<E, SIZE>(&shared_secret);
+
+    Ok((ephemeral_public_key, rescue_encryption_native(params, &key, message)?))
+}
+
+pub fn pke_decryption_native<
+    E: Engine,
+    C: TwistedEdwardsCurveParams<E>,
+    const SIZE: usize,
+    const RNUMBER: usize>(
+    params: &CipherParams<E, SIZE, RNUMBER>,
+    curve: &TwistedEdwardsCurveImplementor<E, C>,
+    secret_key: &C::Fs,
+    ephemeral_public_key: &TwistedEdwardsPoint<E>,
+    ciphertext: &[E::Fr; SIZE])->Result<[E::Fr; SIZE], CipherError>{
+
+    check_point_native(curve, ephemeral_public_key)?;
+    let shared_secret = curve.mul(ephemeral_public_key, secret_key.into_repr());
+    let key = derive_key_native::<E, SIZE>(&shared_secret);
+
+    rescue_decryption_native(params, &key, ciphertext)
+}
+
+// Allocates a public key, enforcing that it is on the curve, in the prime
+// order subgroup and not the identity.
+pub fn alloc_public_key<
+    E: Engine,
+    C: TwistedEdwardsCurveParams<E>,
+    CS: ConstraintSystem<E>>(
+    cs: &mut CS,
+    curve: &CircuitTwistedEdwardsCurveImplementor<E, C>,
+    public_key: Option<TwistedEdwardsPoint<E>>)->Result<CircuitTwistedEdwardsPoint<E>, CipherError>{
+
+    let point = curve.alloc_point_enforce_in_subgroup(cs, public_key)?;
+    enforce_nonzero(cs, &point.x)?;
+    Ok(point)
+}
+
+// Proves that `ciphertext` is the encryption of `message` to `public_key`,
+// which must come from `alloc_public_key`. The ephemeral scalar stays
+// private, the returned ephemeral public key R and ciphertext are meant to
+// be exposed as public inputs.
+pub fn pke_encryption<
+    E: Engine,
+    C: TwistedEdwardsCurveParams<E>,
+    CS: ConstraintSystem<E>,
+    const SIZE: usize,
+    const RNUMBER: usize>(
+    cs: &mut CS,
+    params: &ReadyCipherParams<E, SIZE, RNUMBER>,
+    curve: &CircuitTwistedEdwardsCurveImplementor<E, C>,
+    public_key: &CircuitTwistedEdwardsPoint<E>,
+    ephemeral_key: Option<C::Fs>,
+    message: &[Num<E>; SIZE])->Result<(CircuitTwistedEdwardsPoint<E>, [Num<E>; SIZE]), CipherError>{
+
+    let ephemeral_scalar = alloc_scalar::<E, C::Fs, CS>(cs, ephemeral_key)?;
+
+    // R is a multiple of the generator, so it is in the subgroup and only
+    // the identity, i.e. r = 0, has to be excluded.
+    let ephemeral_public_key = curve.mul_by_generator(cs, &ephemeral_scalar)?;
+    enforce_nonzero(cs, &ephemeral_public_key.x)?;
+    let shared_secret = curve.mul(cs, public_key, &ephemeral_scalar)?;
+    let key = derive_key::<E, CS, SIZE>(cs, &shared_secret)?;
+
+    Ok((ephemeral_public_key, rescue_encryption(cs, params, &key, message)?))
+}
+
+fn derive_key_native<E: Engine, const SIZE: usize>(shared_secret: &TwistedEdwardsPoint<E>) -> [E::Fr; SIZE] {
+    let hash_params = RescueParams::<E, 2, 3>::default();
+    let (x, y) = shared_secret.into_xy();
+    let mut key = [E::Fr::zero(); SIZE];
+    for (i, k) in key.iter_mut().enumerate() {
+        *k = generic_hash(&hash_params, &[x, y, u64_to_fe::<E>(i as u64)], None)[0];
+    }
+    key
+}
+
+fn derive_key<E: Engine, CS: ConstraintSystem<E>, const SIZE: usize>(
+    cs: &mut CS,
+    shared_secret: &CircuitTwistedEdwardsPoint<E>)->Result<[Num<E>; SIZE], CipherError>{
+
+    let hash_params = RescueParams::<E, 2, 3>::default();
+    let mut key = [Num::<E>::zero(); SIZE];
+    for (i, k) in key.iter_mut().enumerate() {
+        let input = [shared_secret.x, shared_secret.y, Num::Constant(u64_to_fe::<E>(i as u64))];
+        *k = circuit_generic_hash_num(cs, &input, &hash_params, None)?[0];
+    }
+    Ok(key)
+}
+
+// The scalar as an element of the circuit field, packed from range checked
+// bits so that every scalar has exactly one encoding.
+fn alloc_scalar<E: Engine, Fs: PrimeField, CS: ConstraintSystem<E>>(
+    cs: &mut CS,
+    scalar: Option<Fs>)->Result<Num<E>, CipherError>{
+
+    let bits = scalar_into_bits::<E, Fs, CS>(cs, scalar)?;
+    let mut lc = LinearCombination::zero();
+    let mut coeff = E::Fr::one();
+    for bit in bits.iter() {
+        lc.add_assign_boolean_with_coeff(bit, coeff);
+        coeff.double();
+    }
+    Ok(lc.into_num(cs)?)
+}
+
+// Little-endian bits of the scalar, enforced to be below the order of the
+// subgroup, so every scalar has exactly one encoding.
+fn scalar_into_bits<E: Engine, Fs: PrimeField, CS: ConstraintSystem<E>>(
+    cs: &mut CS,
+    scalar: Option<Fs>)->Result<Vec<Boolean>, CipherError>{
+
+    let repr = scalar.map(|s| s.into_repr());
+    let mut bits = Vec::with_capacity(Fs::NUM_BITS as usize);
+    for i in 0..(Fs::NUM_BITS as usize) {
+        let value = repr.as_ref().map(|r| bit_of_repr(r.as_ref(), i));
+        bits.push(Boolean::from(AllocatedBit::alloc(cs, value)?));
+    }
+    enforce_below_modulus::<E, Fs, CS>(cs, &bits)?;
+    Ok(bits)
+}
+
+fn bit_of_repr(limbs: &[u64], i: usize) -> bool {
+    (limbs[i / 64] >> (i % 64)) & 1 == 1
+}
+
+// Compares the bits with the modulus of the scalar field from the most
+// significant bit down: the scalar is smaller if at the first differing bit
+// the modulus has a one and the scalar a zero.
+fn enforce_below_modulus<E: Engine, Fs: PrimeField, CS: ConstraintSystem<E>>(
+    cs: &mut CS,
+    bits: &[Boolean])->Result<(), CipherError>{
+
+    let modulus = Fs::char();
+    let mut equal = Boolean::constant(true);
+    let mut less = Boolean::constant(false);
+    for (i, bit) in bits.iter().enumerate().rev() {
+        if bit_of_repr(modulus.as_ref(), i) {
+            let smaller_here = Boolean::and(cs, &equal, &bit.not())?;
+            // less = less or smaller_here
+            less = Boolean::and(cs, &less.not(), &smaller_here.not())?.not();
+            equal = Boolean::and(cs, &equal, bit)?;
+        } else {
+            equal = Boolean::and(cs, &equal, &bit.not())?;
+        }
+    }
+
+    let mut minus_one = E::Fr::one();
+    minus_one.negate();
+    let mut lc = LinearCombination::zero();
+    lc.add_assign_boolean_with_coeff(&less, E::Fr::one());
+    lc.add_assign_constant(minus_one);
+    lc.enforce_zero(cs)?;
+    Ok(())
+}
+
+fn check_point_native<E: Engine, C: TwistedEdwardsCurveParams<E>>(
+    curve: &TwistedEdwardsCurveImplementor<E, C>,
+    point: &TwistedEdwardsPoint<E>)->Result<(), CipherError>{
+
+    let (x, _) = point.into_xy();
+    if x.is_zero() || !curve.is_in_main_subgroup(point) {
+        return Err(CipherError::InvalidPoint);
+    }
+    Ok(())
+}
+
+fn enforce_nonzero<E: Engine, CS: ConstraintSystem<E>>(
+    cs: &mut CS,
+    element: &Num<E>)->Result<(), CipherError>{
+
+    if let Num::Constant(value) = element {
+        if value.is_zero() {
+            return Err(CipherError::InvalidPoint);
+        }
+        return Ok(());
+    }
+
+    let inverse = match element.get_value() {
+        Some(value) => Some(value.inverse().ok_or(CipherError::InvalidPoint)?),
+        None => None
+    };
+    let inverse = Num::alloc(cs, inverse)?;
+    let product = element.mul(cs, &inverse)?;
+    product.enforce_equal(cs, &Num::Constant(E::Fr::one()))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::cipher_tools::generate_cipher_params;
+    use crate::enc_dec::construct_ready_params;
+    use crate::tests::{init_cs, init_rng, test_inputs};
+    use franklin_crypto::alt_babyjubjub::fs::Fs;
+    use franklin_crypto::bellman::pairing::bn256::{Bn256, Fr};
+    use franklin_crypto::twisted_edwards::bn256::AltBabyJubjubParams;
+    use rand::Rand;
+
+    const SIZE: usize = 3;
+    const RNUMBER: usize = 7;
+
+    fn native_curve() -> TwistedEdwardsCurveImplementor<Bn256, AltBabyJubjubParams> {
+        TwistedEdwardsCurveImplementor::new_from_params(AltBabyJubjubParams::new())
+    }
+
+    fn circuit_curve() -> CircuitTwistedEdwardsCurveImplementor<Bn256, AltBabyJubjubParams> {
+        CircuitTwistedEdwardsCurveImplementor::new_from_params(AltBabyJubjubParams::new())
+    }
+
+    // (0, -1) is the point of order 2 on every twisted Edwards curve.
+    fn torsion_point(curve: &TwistedEdwardsCurveImplementor<Bn256, AltBabyJubjubParams>) -> TwistedEdwardsPoint<Bn256> {
+        let mut minus_one = Fr::one();
+        minus_one.negate();
+        curve.from_xy(Fr::zero(), minus_one).unwrap()
+    }
+
+    #[test]
+    fn test_pke_round_trip() {
+        let rng = &mut init_rng();
+        let curve = native_curve();
+        let params = generate_cipher_params::<Bn256, _, SIZE, RNUMBER>(rng);
+        let secret_key = Fs::rand(rng);
+        let public_key = public_key_native(&curve, &secret_key);
+        let message = test_inputs::<Bn256, SIZE>(rng);
+
+        let ephemeral_key = Fs::rand(rng);
+        let (ephemeral_public_key, ciphertext) = pke_encryption_native(&params, &curve, &public_key, &ephemeral_key, &message).unwrap();
+        assert_ne!(ciphertext, message);
+        assert_eq!(pke_decryption_native(&params, &curve, &secret_key, &ephemeral_public_key, &ciphertext).unwrap(), message);
+
+        let wrong_secret_key = Fs::rand(rng);
+        assert_ne!(pke_decryption_native(&params, &curve, &wrong_secret_key, &ephemeral_public_key, &ciphertext).unwrap(), message);
+    }
+
+    #[test]
+    fn test_points_outside_the_subgroup_are_refused() {
+        let rng = &mut init_rng();
+        let curve = native_curve();
+        let params = generate_cipher_params::<Bn256, _, SIZE, RNUMBER>(rng);
+        let message = test_inputs::<Bn256, SIZE>(rng);
+        let identity = curve.from_xy(Fr::zero(), Fr::one()).unwrap();
+        let public_key = public_key_native(&curve, &Fs::rand(rng));
+        // a valid key with a torsion component added
+        let mixed_order_key = curve.add(&public_key, &torsion_point(&curve));
+
+        for point in [identity.clone(), torsion_point(&curve), mixed_order_key.clone()].iter() {
+            assert!(matches!(
+                pke_encryption_native(&params, &curve, point, &Fs::rand(rng), &message),
+                Err(CipherError::InvalidPoint)
+            ));
+            assert!(matches!(
+                pke_decryption_native(&params, &curve, &Fs::rand(rng), point, &message),
+                Err(CipherError::InvalidPoint)
+            ));
+        }
+
+        let cs = &mut init_cs::<Bn256>();
+        assert!(matches!(
+            alloc_public_key(cs, &circuit_curve(), Some(identity)),
+            Err(CipherError::InvalidPoint)
+        ));
+
+        let cs = &mut init_cs::<Bn256>();
+        alloc_public_key(cs, &circuit_curve(), Some(mixed_order_key)).unwrap();
+        cs.finalize();
+        assert!(!cs.is_satisfied());
+    }
+
+    #[test]
+    fn test_pke_native_matches_circuit() {
+        let rng = &mut init_rng();
+        let cs = &mut init_cs::<Bn256>();
+        let curve = native_curve();
+        let params = generate_cipher_params::<Bn256, _, SIZE, RNUMBER>(rng);
+        let secret_key = Fs::rand(rng);
+        let public_key = public_key_native(&curve, &secret_key);
+        let message = test_inputs::<Bn256, SIZE>(rng);
+        let ephemeral_key = Fs::rand(rng);
+
+        let (expected_ephemeral_public_key, expected_ciphertext) = pke_encryption_native(&params, &curve, &public_key, &ephemeral_key, &message).unwrap();
+
+        let circuit_curve = circuit_curve();
+        let public_key_as_point = alloc_public_key(cs, &circuit_curve, Some(public_key)).unwrap();
+        let mut message_as_nums = [Num::<Bn256>::zero(); SIZE];
+        for i in 0..SIZE {
+            message_as_nums[i] = Num::alloc(cs, Some(message[i])).unwrap();
+        }
+
+        let ready_params = construct_ready_params(&params).unwrap();
+        let (ephemeral_public_key, ciphertext) = pke_encryption(cs, &ready_params, &circuit_curve, &public_key_as_point, Some(ephemeral_key), &message_as_nums).unwrap();
+
+        let (expected_x, expected_y) = expected_ephemeral_public_key.into_xy();
+        assert_eq!(ephemeral_public_key.x.get_value().unwrap(), expected_x);
+        assert_eq!(ephemeral_public_key.y.get_value().unwrap(), expected_y);
+        for i in 0..SIZE {
+            assert_eq!(ciphertext[i].get_value().unwrap(), expected_ciphertext[i]);
+        }
+
+        cs.finalize();
+        assert!(cs.is_satisfied());
+    }
+}
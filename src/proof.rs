@@ -0,0 +1,170 @@
+use franklin_crypto::plonk::circuit::allocated_num::{AllocatedNum, Num};
+use franklin_crypto::bellman::pairing::Engine;
+use franklin_crypto::bellman::SynthesisError;
+use franklin_crypto::bellman::worker::Worker;
+use franklin_crypto::bellman::kate_commitment::{Crs, CrsForMonomialForm};
+use franklin_crypto::bellman::plonk::better_better_cs::cs::{
+    Circuit, ConstraintSystem, PlonkCsWidth4WithNextStepParams, ProvingAssembly, SetupAssembly,
+    Width4MainGateWithDNext
+};
+use franklin_crypto::bellman::plonk::better_better_cs::setup::{Setup, VerificationKey};
+use franklin_crypto::bellman::plonk::better_better_cs::proof::Proof;
+use franklin_crypto::bellman::plonk::better_better_cs::verifier::verify;
+use franklin_crypto::bellman::plonk::commitments::transcript::keccak_transcript::RollingKeccakTranscript;
+use super::cipher_tools::CipherParams;
+use super::enc_dec::{ReadyCipherParams, construct_ready_params, rescue_encryption};
+use super::error::CipherError;
+
+// Proves knowledge of a key and a plaintext that encrypt to the ciphertext.
+// The ciphertext is the only public input, in the order of its elements.
+pub struct RescueEncryptionCircuit<
+    E: Engine,
+    const SIZE: usize,
+    const RNUMBER: usize> {
+    pub params: ReadyCipherParams<E, SIZE, RNUMBER>,
+    pub key: Option<[E::Fr; SIZE]>,
+    pub plaintext: Option<[E::Fr; SIZE]>
+}
+
+impl<E: Engine, const SIZE: usize, const RNUMBER: usize> RescueEncryptionCircuit<E, SIZE, RNUMBER> {
+    pub fn new(
+        params: &CipherParams<E, SIZE, RNUMBER>,
+        key: Option<[E::Fr; SIZE]>,
+        plaintext: Option<[E::Fr; SIZE]>)->Result<Self, CipherError>{
+        Ok(RescueEncryptionCircuit {
+            params: construct_ready_params(params)?,
+            key,
+            plaintext
+        })
+    }
+}
+
+impl<E: Engine, const SIZE: usize, const RNUMBER: usize> Circuit<E> for RescueEncryptionCircuit<E, SIZE, RNUMBER> {
+    type MainGate = Width4MainGateWithDNext;
+
+    fn synthesize<CS: ConstraintSystem<E>>(&self, cs: &mut CS) -> Result<(), SynthesisError> {
+        let mut key = [Num::<E>::zero(); SIZE];
+        let mut plaintext = [Num::<E>::zero(); SIZE];
+        for i in 0..SIZE {
+            key[i] = Num::alloc(cs, self.key.map(|k| k[i]))?;
+            plaintext[i] = Num::alloc(cs, self.plaintext.map(|p| p[i]))?;
+        }
+
+        // the parameters were checked in `new`, so only synthesis can fail here
+        let ciphertext = rescue_encryption(cs, &self.params, &key, &plaintext).map_err(|err| match err {
+            CipherError::Synthesis(err) => err,
+            _ => SynthesisError::Unsatisfiable
+        })?;
+
+        for element in ciphertext.iter() {
+            let input = AllocatedNum::alloc_input(cs, || {
+                element.get_value().ok_or(SynthesisError::AssignmentMissing)
+            })?;
+            element.enforce_equal(cs, &Num::Variable(input))?;
+        }
+
+        Ok(())
+    }
+}
+
+pub type EncryptionSetup<E, const SIZE: usize, const RNUMBER: usize> = Setup<E, RescueEncryptionCircuit<E, SIZE, RNUMBER>>;
+pub type EncryptionVerificationKey<E, const SIZE: usize, const RNUMBER: usize> = VerificationKey<E, RescueEncryptionCircuit<E, SIZE, RNUMBER>>;
+pub type EncryptionProof<E, const SIZE: usize, const RNUMBER: usize> = Proof<E, RescueEncryptionCircuit<E, SIZE, RNUMBER>>;
+
+// Generates the setup, a monomial form CRS and the verification key.
+// The CRS comes from a fixed local toxic waste, so it is only suitable for
+// tests and experiments, never for production proofs.
+pub fn generate_encryption_setup<
+    E: Engine,
+    const SIZE: usize,
+    const RNUMBER: usize>(
+    params: &CipherParams<E, SIZE, RNUMBER>,
+    worker: &Worker
+    )->Result<(EncryptionSetup<E, SIZE, RNUMBER>, Crs<E, CrsForMonomialForm>, EncryptionVerificationKey<E, SIZE, RNUMBER>), CipherError>{
+
+    let circuit = RescueEncryptionCircuit::new(params, None, None)?;
+    let mut assembly = SetupAssembly::<E, PlonkCsWidth4WithNextStepParams, Width4MainGateWithDNext>::new();
+    circuit.synthesize(&mut assembly)?;
+    assembly.finalize();
+
+    let setup = assembly.create_setup::<RescueEncryptionCircuit<E, SIZE, RNUMBER>>(worker)?;
+    let crs = Crs::<E, CrsForMonomialForm>::crs_42(setup.permutation_monomials[0].size(), worker);
+    let verification_key = VerificationKey::from_setup(&setup, worker, &crs)?;
+
+    Ok((setup, crs, verification_key))
+}
+
+pub fn create_encryption_proof<
+    E: Engine,
+    const SIZE: usize,
+    const RNUMBER: usize>(
+    params: &CipherParams<E, SIZE, RNUMBER>,
+    setup: &EncryptionSetup<E, SIZE, RNUMBER>,
+    crs: &Crs<E, CrsForMonomialForm>,
+    key: &[E::Fr; SIZE],
+    plaintext: &[E::Fr; SIZE],
+    worker: &Worker
+    )->Result<EncryptionProof<E, SIZE, RNUMBER>, CipherError>{
+
+    let circuit = RescueEncryptionCircuit::new(params, Some(*key), Some(*plaintext))?;
+    let mut assembly = ProvingAssembly::<E, PlonkCsWidth4WithNextStepParams, Width4MainGateWithDNext>::new();
+    circuit.synthesize(&mut assembly)?;
+    assembly.finalize();
+
+    let proof = assembly.create_proof::<RescueEncryptionCircuit<E, SIZE, RNUMBER>, RollingKeccakTranscript<E::Fr>>(
+        worker, setup, crs, None
+    )?;
+    Ok(proof)
+}
+
+// Checks the proof and that its public inputs are exactly the ciphertext.
+pub fn verify_encryption_proof<
+    E: Engine,
+    const SIZE: usize,
+    const RNUMBER: usize>(
+    verification_key: &EncryptionVerificationKey<E, SIZE, RNUMBER>,
+    proof: &EncryptionProof<E, SIZE, RNUMBER>,
+    ciphertext: &[E::Fr; SIZE])->Result<bool, CipherError>{
+
+    if proof.inputs[..] != ciphertext[..] {
+        return Ok(false);
+    }
+
+    let valid = verify::<E, RescueEncryptionCircuit<E, SIZE, RNUMBER>, RollingKeccakTranscript<E::Fr>>(verification_key, proof, None)?;
+    Ok(valid)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::cipher_tools::generate_cipher_params;
+    use crate::native::rescue_encryption_native;
+    use crate::tests::{init_rng, test_inputs};
+    use franklin_crypto::bellman::pairing::bn256::Bn256;
+    use franklin_crypto::bellman::pairing::ff::Field;
+
+    #[test]
+    fn test_encryption_proof() {
+        const SIZE: usize = 3;
+        const RNUMBER: usize = 7;
+        let rng = &mut init_rng();
+        let worker = Worker::new();
+        let params = generate_cipher_params::<Bn256, _, SIZE, RNUMBER>(rng);
+        let key = test_inputs::<Bn256, SIZE>(rng);
+        let plaintext = test_inputs::<Bn256, SIZE>(rng);
+        let ciphertext = rescue_encryption_native(&params, &key, &plaintext).unwrap();
+
+        let (setup, crs, verification_key) = generate_encryption_setup(&params, &worker).unwrap();
+        let proof = create_encryption_proof(&params, &setup, &crs, &key, &plaintext, &worker).unwrap();
+        assert_eq!(proof.inputs, ciphertext.to_vec());
+        assert!(verify_encryption_proof(&verification_key, &proof, &ciphertext).unwrap());
+
+        let mut wrong_ciphertext = ciphertext;
+        wrong_ciphertext[0].add_assign(&<Bn256 as Engine>::Fr::one());
+        assert!(!verify_encryption_proof(&verification_key, &proof, &wrong_ciphertext).unwrap());
+
+        let mut forged_proof = proof;
+        forged_proof.inputs = wrong_ciphertext.to_vec();
+        assert!(!verify_encryption_proof(&verification_key, &forged_proof, &wrong_ciphertext).unwrap());
+    }
+}
@@ -0,0 +1,610 @@
+use crate::cipher_tools::{
+    defaults::{BN256_SIZE_2_TAG, BN256_SIZE_3_TAG, BN256_SIZE_4_TAG, BN256_SIZE_5_TAG},
+    generate_cipher_params, generate_cipher_params_from_tag, generate_cipher_params_with_alpha,
+    generate_secure_cipher_params, rounds::DEFAULT_SECURITY_LEVEL,
+    sboxes::{compute_alpha, is_valid_alpha, AlphaInverseSBox, AlphaSBox},
+    CipherParams,
+};
+use crate::error::CipherError;
+use crate::cipher_tools::mds::{
+    add_vectors, construct_inverse_matrix_native, construct_mds_matrix_native, lcs_into_nums,
+    nums_into_lcs,
+};
+use crate::enc_dec::{
+    construct_ready_params, encryption_round, rescue_decryption, rescue_decryption_with_key_schedule,
+    rescue_encryption, rescue_encryption_conditional, rescue_encryption_conditional_with_dummy,
+    rescue_encryption_with_key_schedule, rescue_tweakable_decryption, rescue_tweakable_encryption,
+    KeySchedule,
+};
+use crate::native::{
+    rescue_decryption_native, rescue_decryption_with_key_schedule_native, rescue_encryption_native,
+    rescue_encryption_trace_native, rescue_encryption_with_key_schedule_native,
+    rescue_tweakable_decryption_native, rescue_tweakable_encryption_native, NativeKeySchedule,
+};
+use franklin_crypto::bellman::pairing::bn256::{Bn256, Fr};
+use franklin_crypto::bellman::pairing::ff::{PrimeField, PrimeFieldRepr};
+use franklin_crypto::bellman::plonk::better_better_cs::cs::{
+    ConstraintSystem, PlonkCsWidth4WithNextStepParams, SetupAssembly, TrivialAssembly,
+    Width4MainGateWithDNext,
+};
+use franklin_crypto::bellman::{Engine, Field};
+use franklin_crypto::plonk::circuit::allocated_num::Num;
+use franklin_crypto::plonk::circuit::boolean::{AllocatedBit, Boolean};
+use franklin_crypto::plonk::circuit::Width4WithCustomGates;
+use rand::{Rand, SeedableRng, XorShiftRng};
+use serde::Deserialize;
+
+pub(crate) const TEST_SEED: [u32; 4] = [0x5dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654];
+
+pub(crate) fn init_rng() -> XorShiftRng {
+    XorShiftRng::from_seed(TEST_SEED)
+}
+
+pub(crate) fn init_cs<E: Engine>(
+) -> TrivialAssembly<E, PlonkCsWidth4WithNextStepParams, Width4MainGateWithDNext> {
+    TrivialAssembly::<E, PlonkCsWidth4WithNextStepParams, Width4MainGateWithDNext>::new()
+}
+
+pub(crate) fn init_cs_with_custom_gates<E: Engine>(
+) -> TrivialAssembly<E, Width4WithCustomGates, Width4MainGateWithDNext> {
+    TrivialAssembly::<E, Width4WithCustomGates, Width4MainGateWithDNext>::new()
+}
+
+pub(crate) fn test_inputs<E: Engine, const SIZE: usize>(rng: &mut XorShiftRng) -> [E::Fr; SIZE] {
+    let mut inputs = [E::Fr::zero(); SIZE];
+    for inp in inputs.iter_mut() {
+        *inp = E::Fr::rand(rng);
+    }
+    inputs
+}
+
+fn test_native_round_trip<E: Engine, const SIZE: usize, const RNUMBER: usize>() {
+    let rng = &mut init_rng();
+    let params: CipherParams<E, SIZE, RNUMBER> = generate_cipher_params(rng);
+
+    for _ in 0..4 {
+        let key = test_inputs::<E, SIZE>(rng);
+        let plaintext = test_inputs::<E, SIZE>(rng);
+
+        let ciphertext = rescue_encryption_native(&params, &key, &plaintext).unwrap();
+        assert_ne!(ciphertext, plaintext);
+        let decrypted = rescue_decryption_native(&params, &key, &ciphertext).unwrap();
+        assert_eq!(decrypted, plaintext);
+    }
+}
+
+fn test_native_matches_circuit<E: Engine, const SIZE: usize, const RNUMBER: usize>() {
+    let rng = &mut init_rng();
+    let cs = &mut init_cs::<E>();
+    let params: CipherParams<E, SIZE, RNUMBER> = generate_cipher_params(rng);
+
+    let key = test_inputs::<E, SIZE>(rng);
+    let plaintext = test_inputs::<E, SIZE>(rng);
+
+    let expected_ciphertext = rescue_encryption_native(&params, &key, &plaintext).unwrap();
+    let expected_plaintext = rescue_decryption_native(&params, &key, &expected_ciphertext).unwrap();
+    assert_eq!(expected_plaintext, plaintext);
+
+    let mut key_as_nums = [Num::<E>::zero(); SIZE];
+    let mut plaintext_as_nums = [Num::<E>::zero(); SIZE];
+    for i in 0..SIZE {
+        key_as_nums[i] = Num::alloc(cs, Some(key[i])).unwrap();
+        plaintext_as_nums[i] = Num::alloc(cs, Some(plaintext[i])).unwrap();
+    }
+
+    let ready_params = construct_ready_params(&params).unwrap();
+    let ciphertext = rescue_encryption(cs, &ready_params, &key_as_nums, &plaintext_as_nums).unwrap();
+    let decrypted = rescue_decryption(cs, &ready_params, &key_as_nums, &ciphertext).unwrap();
+
+    for i in 0..SIZE {
+        assert_eq!(ciphertext[i].get_value().unwrap(), expected_ciphertext[i]);
+        assert_eq!(decrypted[i].get_value().unwrap(), plaintext[i]);
+    }
+
+    cs.finalize();
+    assert!(cs.is_satisfied());
+}
+
+fn test_key_schedule_reuse<E: Engine, const SIZE: usize, const RNUMBER: usize>() {
+    let rng = &mut init_rng();
+    let cs = &mut init_cs::<E>();
+    let params: CipherParams<E, SIZE, RNUMBER> = generate_cipher_params(rng);
+
+    let key = test_inputs::<E, SIZE>(rng);
+    let plaintexts = [test_inputs::<E, SIZE>(rng), test_inputs::<E, SIZE>(rng)];
+
+    let native_key_schedule = NativeKeySchedule::new(&params, &key).unwrap();
+
+    let mut key_as_nums = [Num::<E>::zero(); SIZE];
+    for i in 0..SIZE {
+        key_as_nums[i] = Num::alloc(cs, Some(key[i])).unwrap();
+    }
+    let ready_params = construct_ready_params(&params).unwrap();
+
+    let start = cs.n();
+    let key_schedule = KeySchedule::new(cs, &ready_params, &key_as_nums).unwrap();
+    let key_schedule_gates = cs.n() - start;
+    assert!(key_schedule_gates > 0);
+
+    for plaintext in plaintexts.iter() {
+        let expected = rescue_encryption_with_key_schedule_native(&native_key_schedule, plaintext);
+        assert_eq!(expected, rescue_encryption_native(&params, &key, plaintext).unwrap());
+
+        let mut plaintext_as_nums = [Num::<E>::zero(); SIZE];
+        for i in 0..SIZE {
+            plaintext_as_nums[i] = Num::alloc(cs, Some(plaintext[i])).unwrap();
+        }
+
+        let start = cs.n();
+        let ciphertext = rescue_encryption_with_key_schedule(cs, &ready_params, &key_schedule, &plaintext_as_nums).unwrap();
+        let with_schedule_gates = cs.n() - start;
+
+        let start = cs.n();
+        let _ = rescue_encryption(cs, &ready_params, &key_as_nums, &plaintext_as_nums).unwrap();
+        let without_schedule_gates = cs.n() - start;
+        assert_eq!(without_schedule_gates, with_schedule_gates + key_schedule_gates);
+
+        let decrypted = rescue_decryption_with_key_schedule(cs, &ready_params, &key_schedule, &ciphertext).unwrap();
+        for i in 0..SIZE {
+            assert_eq!(ciphertext[i].get_value().unwrap(), expected[i]);
+            assert_eq!(decrypted[i].get_value().unwrap(), plaintext[i]);
+        }
+    }
+
+    cs.finalize();
+    assert!(cs.is_satisfied());
+}
+
+// Known answers for fixed BN256 parameters, see test_vectors/generate.py.
+// Elements are hex strings of their big-endian bytes and the parameters use
+// the JSON format of `CipherParams::to_json`. Sets with a tag hold the
+// default parameters derived from it.
+#[derive(Deserialize)]
+struct KnownAnswerSet {
+    size: usize,
+    rnumber: usize,
+    tag: Option<String>,
+    params: serde_json::Value,
+    cases: Vec<KnownAnswerCase>,
+}
+
+#[derive(Deserialize)]
+struct KnownAnswerCase {
+    key: Vec<String>,
+    plaintext: Vec<String>,
+    subkeys: Vec<Vec<String>>,
+    round_states: Vec<Vec<String>>,
+    ciphertext: Vec<String>,
+}
+
+fn block_from_hex<const SIZE: usize>(encoded: &[String]) -> [Fr; SIZE] {
+    assert_eq!(encoded.len(), SIZE);
+    let mut block = [Fr::zero(); SIZE];
+    for (element, encoded) in block.iter_mut().zip(encoded.iter()) {
+        let bytes = hex::decode(encoded).unwrap();
+        let mut repr = <Fr as PrimeField>::Repr::default();
+        repr.read_be(&bytes[..]).unwrap();
+        *element = Fr::from_repr(repr).unwrap();
+    }
+    block
+}
+
+fn blocks_from_hex<const SIZE: usize, const RNUMBER: usize>(encoded: &[Vec<String>]) -> [[Fr; SIZE]; RNUMBER] {
+    assert_eq!(encoded.len(), RNUMBER);
+    let mut blocks = [[Fr::zero(); SIZE]; RNUMBER];
+    for (block, encoded) in blocks.iter_mut().zip(encoded.iter()) {
+        *block = block_from_hex::<SIZE>(encoded);
+    }
+    blocks
+}
+
+fn test_known_answers<const SIZE: usize, const RNUMBER: usize>(set: &KnownAnswerSet) {
+    let params = CipherParams::<Bn256, SIZE, RNUMBER>::from_json(&set.params.to_string()).unwrap();
+    let ready_params = construct_ready_params(&params).unwrap();
+    if let Some(tag) = &set.tag {
+        let params_from_tag = generate_cipher_params_from_tag::<Bn256, SIZE, RNUMBER>(tag.as_bytes()).unwrap();
+        assert_eq!(params_from_tag.to_bytes(), params.to_bytes());
+    }
+
+    for case in set.cases.iter() {
+        let key = block_from_hex::<SIZE>(&case.key);
+        let plaintext = block_from_hex::<SIZE>(&case.plaintext);
+        let ciphertext = block_from_hex::<SIZE>(&case.ciphertext);
+        let subkeys = blocks_from_hex::<SIZE, RNUMBER>(&case.subkeys);
+        let round_states = blocks_from_hex::<SIZE, RNUMBER>(&case.round_states);
+
+        let native_key_schedule = NativeKeySchedule::new(&params, &key).unwrap();
+        assert_eq!(native_key_schedule.subkeys, subkeys);
+        let trace = rescue_encryption_trace_native(&native_key_schedule, &plaintext);
+        assert_eq!(trace, round_states);
+        assert_eq!(rescue_encryption_native(&params, &key, &plaintext).unwrap(), ciphertext);
+        assert_eq!(rescue_decryption_with_key_schedule_native(&native_key_schedule, &ciphertext), plaintext);
+
+        let cs = &mut init_cs::<Bn256>();
+        let mut key_as_nums = [Num::<Bn256>::zero(); SIZE];
+        let mut plaintext_as_nums = [Num::<Bn256>::zero(); SIZE];
+        for i in 0..SIZE {
+            key_as_nums[i] = Num::alloc(cs, Some(key[i])).unwrap();
+            plaintext_as_nums[i] = Num::alloc(cs, Some(plaintext[i])).unwrap();
+        }
+        let key_schedule = KeySchedule::new(cs, &ready_params, &key_as_nums).unwrap();
+        for i in 0..RNUMBER {
+            for j in 0..SIZE {
+                assert_eq!(key_schedule.subkeys[i][j].get_value().unwrap(), subkeys[i][j]);
+            }
+        }
+
+        // same rounds as `rescue_encryption_with_key_schedule`, checked one by one
+        let mut state = nums_into_lcs(&plaintext_as_nums);
+        add_vectors(&mut state, &key_schedule.subkeys[0]);
+        for i in 0..RNUMBER {
+            if i > 0 {
+                state = encryption_round(cs, &ready_params, state, &key_schedule.subkeys[i], i).unwrap();
+            }
+            for j in 0..SIZE {
+                let element = state[j].clone().into_num(cs).unwrap();
+                assert_eq!(element.get_value().unwrap(), round_states[i][j]);
+            }
+        }
+        let ciphertext_as_nums = lcs_into_nums(cs, state).unwrap();
+        let decrypted = rescue_decryption_with_key_schedule(cs, &ready_params, &key_schedule, &ciphertext_as_nums).unwrap();
+        for i in 0..SIZE {
+            assert_eq!(ciphertext_as_nums[i].get_value().unwrap(), ciphertext[i]);
+            assert_eq!(decrypted[i].get_value().unwrap(), plaintext[i]);
+        }
+
+        cs.finalize();
+        assert!(cs.is_satisfied());
+    }
+}
+
+fn test_conditional_encryption<E: Engine, const SIZE: usize, const RNUMBER: usize>() {
+    let rng = &mut init_rng();
+    let params: CipherParams<E, SIZE, RNUMBER> = generate_cipher_params(rng);
+    let ready_params = construct_ready_params(&params).unwrap();
+    let key = test_inputs::<E, SIZE>(rng);
+    let plaintext = test_inputs::<E, SIZE>(rng);
+    let dummy = test_inputs::<E, SIZE>(rng);
+    let ciphertext = rescue_encryption_native(&params, &key, &plaintext).unwrap();
+    let dummy_ciphertext = rescue_encryption_native(&params, &key, &dummy).unwrap();
+
+    let mut gates = vec![];
+    for flag in [true, false].iter() {
+        let cs = &mut init_cs::<E>();
+        let mut key_as_nums = [Num::<E>::zero(); SIZE];
+        let mut plaintext_as_nums = [Num::<E>::zero(); SIZE];
+        let mut dummy_as_nums = [Num::<E>::zero(); SIZE];
+        for i in 0..SIZE {
+            key_as_nums[i] = Num::alloc(cs, Some(key[i])).unwrap();
+            plaintext_as_nums[i] = Num::alloc(cs, Some(plaintext[i])).unwrap();
+            dummy_as_nums[i] = Num::alloc(cs, Some(dummy[i])).unwrap();
+        }
+        let flag_as_boolean = Boolean::from(AllocatedBit::alloc(cs, Some(*flag)).unwrap());
+
+        let start = cs.n();
+        let output = rescue_encryption_conditional(cs, &ready_params, &key_as_nums, &plaintext_as_nums, &flag_as_boolean).unwrap();
+        gates.push(cs.n() - start);
+
+        let key_schedule = KeySchedule::new(cs, &ready_params, &key_as_nums).unwrap();
+        let start = cs.n();
+        let dummy_output = rescue_encryption_conditional_with_dummy(
+            cs, &ready_params, &key_schedule, &plaintext_as_nums, &dummy_as_nums, &flag_as_boolean
+        ).unwrap();
+        gates.push(cs.n() - start);
+
+        for i in 0..SIZE {
+            let expected = if *flag { ciphertext[i] } else { plaintext[i] };
+            assert_eq!(output[i].get_value().unwrap(), expected);
+            let expected = if *flag { ciphertext[i] } else { dummy_ciphertext[i] };
+            assert_eq!(dummy_output[i].get_value().unwrap(), expected);
+        }
+
+        cs.finalize();
+        assert!(cs.is_satisfied());
+    }
+    assert_eq!(gates[0], gates[2]);
+    assert_eq!(gates[1], gates[3]);
+}
+
+fn test_tweakable_cipher<E: Engine, const SIZE: usize, const RNUMBER: usize>() {
+    let rng = &mut init_rng();
+    let params: CipherParams<E, SIZE, RNUMBER> = generate_cipher_params(rng);
+    let key = test_inputs::<E, SIZE>(rng);
+    let plaintext = test_inputs::<E, SIZE>(rng);
+    let tweaks = [E::Fr::one(), E::Fr::rand(rng)];
+
+    let ciphertexts = [
+        rescue_tweakable_encryption_native(&params, &key, &tweaks[0], &plaintext).unwrap(),
+        rescue_tweakable_encryption_native(&params, &key, &tweaks[1], &plaintext).unwrap(),
+    ];
+    assert_ne!(ciphertexts[0], ciphertexts[1]);
+    assert_ne!(ciphertexts[0], rescue_encryption_native(&params, &key, &plaintext).unwrap());
+    assert_eq!(
+        rescue_tweakable_encryption_native(&params, &key, &E::Fr::zero(), &plaintext).unwrap(),
+        rescue_encryption_native(&params, &key, &plaintext).unwrap()
+    );
+    for (tweak, ciphertext) in tweaks.iter().zip(ciphertexts.iter()) {
+        assert_eq!(rescue_tweakable_decryption_native(&params, &key, tweak, ciphertext).unwrap(), plaintext);
+    }
+
+    let cs = &mut init_cs::<E>();
+    let mut key_as_nums = [Num::<E>::zero(); SIZE];
+    let mut plaintext_as_nums = [Num::<E>::zero(); SIZE];
+    for i in 0..SIZE {
+        key_as_nums[i] = Num::alloc(cs, Some(key[i])).unwrap();
+        plaintext_as_nums[i] = Num::alloc(cs, Some(plaintext[i])).unwrap();
+    }
+    let ready_params = construct_ready_params(&params).unwrap();
+    for (tweak, expected) in tweaks.iter().zip(ciphertexts.iter()) {
+        let tweak_as_num = Num::alloc(cs, Some(*tweak)).unwrap();
+        let ciphertext = rescue_tweakable_encryption(cs, &ready_params, &key_as_nums, &tweak_as_num, &plaintext_as_nums).unwrap();
+        let decrypted = rescue_tweakable_decryption(cs, &ready_params, &key_as_nums, &tweak_as_num, &ciphertext).unwrap();
+        for i in 0..SIZE {
+            assert_eq!(ciphertext[i].get_value().unwrap(), expected[i]);
+            assert_eq!(decrypted[i].get_value().unwrap(), plaintext[i]);
+        }
+    }
+    cs.finalize();
+    assert!(cs.is_satisfied());
+}
+
+fn synthesize_round_trip<E: Engine, CS: ConstraintSystem<E>, const SIZE: usize, const RNUMBER: usize>(
+    cs: &mut CS,
+    params: &CipherParams<E, SIZE, RNUMBER>,
+    key: Option<[E::Fr; SIZE]>,
+    plaintext: Option<[E::Fr; SIZE]>,
+) -> usize {
+    let mut key_as_nums = [Num::<E>::zero(); SIZE];
+    let mut plaintext_as_nums = [Num::<E>::zero(); SIZE];
+    for i in 0..SIZE {
+        key_as_nums[i] = Num::alloc(cs, key.map(|k| k[i])).unwrap();
+        plaintext_as_nums[i] = Num::alloc(cs, plaintext.map(|p| p[i])).unwrap();
+    }
+    let ready_params = construct_ready_params(params).unwrap();
+
+    let ciphertext = rescue_encryption(cs, &ready_params, &key_as_nums, &plaintext_as_nums).unwrap();
+    let decrypted = rescue_decryption(cs, &ready_params, &key_as_nums, &ciphertext).unwrap();
+    for i in 0..SIZE {
+        plaintext_as_nums[i].enforce_equal(cs, &decrypted[i]).unwrap();
+    }
+
+    cs.get_current_step_number()
+}
+
+// Setup and verification key generation run without any witness values.
+fn test_setup_mode_synthesis<E: Engine, const SIZE: usize, const RNUMBER: usize>() {
+    let rng = &mut init_rng();
+    let params: CipherParams<E, SIZE, RNUMBER> = generate_cipher_params(rng);
+
+    let mut setup_cs = SetupAssembly::<E, PlonkCsWidth4WithNextStepParams, Width4MainGateWithDNext>::new();
+    let setup_gates = synthesize_round_trip(&mut setup_cs, &params, None, None);
+    setup_cs.finalize();
+
+    let cs = &mut init_cs::<E>();
+    let key = test_inputs::<E, SIZE>(rng);
+    let plaintext = test_inputs::<E, SIZE>(rng);
+    let proving_gates = synthesize_round_trip(cs, &params, Some(key), Some(plaintext));
+    assert_eq!(setup_gates, proving_gates);
+    cs.finalize();
+    assert!(cs.is_satisfied());
+}
+
+fn test_sbox_costs<E: Engine, CS: ConstraintSystem<E>, const SIZE: usize>(cs: &mut CS, alpha: u64) -> (usize, usize) {
+    let rng = &mut init_rng();
+    let sbox = AlphaSBox::<E, SIZE>::new(alpha).unwrap();
+    let inverse_sbox = AlphaInverseSBox::<E, SIZE>::new(alpha).unwrap();
+
+    let inputs = test_inputs::<E, SIZE>(rng);
+    let mut expected = inputs;
+    let mut elements = [Num::<E>::zero(); SIZE];
+    for i in 0..SIZE {
+        elements[i] = Num::alloc(cs, Some(expected[i])).unwrap();
+    }
+
+    let start = cs.get_current_step_number();
+    sbox.apply(cs, &mut elements).unwrap();
+    let sbox_gates = cs.get_current_step_number() - start;
+    sbox.apply_native(&mut expected);
+
+    let start = cs.get_current_step_number();
+    inverse_sbox.apply(cs, &mut elements).unwrap();
+    let inverse_sbox_gates = cs.get_current_step_number() - start;
+    inverse_sbox.apply_native(&mut expected);
+
+    for i in 0..SIZE {
+        assert_eq!(elements[i].get_value().unwrap(), expected[i]);
+        assert_eq!(expected[i], inputs[i]);
+    }
+
+    (sbox_gates, inverse_sbox_gates)
+}
+
+#[test]
+fn test_rescue_sbox_costs() {
+    const SIZE: usize = 3;
+
+    let cs = &mut init_cs::<Bn256>();
+    let (sbox_gates, inverse_sbox_gates) = test_sbox_costs::<_, _, SIZE>(cs, 5);
+    assert!(sbox_gates <= 3 * SIZE);
+    assert!(inverse_sbox_gates <= 3 * SIZE);
+    cs.finalize();
+    assert!(cs.is_satisfied());
+
+    let cs = &mut init_cs_with_custom_gates::<Bn256>();
+    let (custom_sbox_gates, custom_inverse_sbox_gates) = test_sbox_costs::<_, _, SIZE>(cs, 5);
+    assert!(custom_sbox_gates <= sbox_gates);
+    assert!(custom_inverse_sbox_gates <= inverse_sbox_gates);
+    cs.finalize();
+    assert!(cs.is_satisfied());
+}
+
+#[test]
+fn test_rescue_alpha_selection() {
+    // p - 1 is divisible by 3 for BN256, so the smallest valid alpha is 5.
+    assert_eq!(compute_alpha::<Bn256>(), 5);
+    assert!(!is_valid_alpha::<Bn256>(3));
+    assert!(!is_valid_alpha::<Bn256>(4));
+    assert!(is_valid_alpha::<Bn256>(7));
+    assert!(is_valid_alpha::<Bn256>(11));
+    assert!(matches!(AlphaSBox::<Bn256, 3>::new(3), Err(CipherError::InvalidAlpha(3))));
+    assert!(matches!(
+        generate_cipher_params_with_alpha::<Bn256, _, 3, 7>(&mut init_rng(), 9),
+        Err(CipherError::InvalidAlpha(9))
+    ));
+}
+
+#[test]
+fn test_rescue_generic_alpha_sbox() {
+    const SIZE: usize = 3;
+
+    // alpha = 7 is 111 in binary: two squarings and two multiplications.
+    for (alpha, gates) in [(7, 4), (11, 5)].iter() {
+        let cs = &mut init_cs::<Bn256>();
+        let (sbox_gates, inverse_sbox_gates) = test_sbox_costs::<_, _, SIZE>(cs, *alpha);
+        assert_eq!(sbox_gates, gates * SIZE);
+        assert_eq!(inverse_sbox_gates, gates * SIZE);
+        cs.finalize();
+        assert!(cs.is_satisfied());
+    }
+}
+
+#[test]
+fn test_rescue_native_matches_circuit_with_alpha() {
+    const SIZE: usize = 3;
+    const RNUMBER: usize = 7;
+    let rng = &mut init_rng();
+    let cs = &mut init_cs::<Bn256>();
+    let params: CipherParams<Bn256, SIZE, RNUMBER> = generate_cipher_params_with_alpha(rng, 7).unwrap();
+    let key = test_inputs::<Bn256, SIZE>(rng);
+    let plaintext = test_inputs::<Bn256, SIZE>(rng);
+
+    let expected = rescue_encryption_native(&params, &key, &plaintext).unwrap();
+    assert_eq!(rescue_decryption_native(&params, &key, &expected).unwrap(), plaintext);
+
+    let mut key_as_nums = [Num::<Bn256>::zero(); SIZE];
+    let mut plaintext_as_nums = [Num::<Bn256>::zero(); SIZE];
+    for i in 0..SIZE {
+        key_as_nums[i] = Num::alloc(cs, Some(key[i])).unwrap();
+        plaintext_as_nums[i] = Num::alloc(cs, Some(plaintext[i])).unwrap();
+    }
+    let ready_params = construct_ready_params(&params).unwrap();
+    let ciphertext = rescue_encryption(cs, &ready_params, &key_as_nums, &plaintext_as_nums).unwrap();
+    for i in 0..SIZE {
+        assert_eq!(ciphertext[i].get_value().unwrap(), expected[i]);
+    }
+    cs.finalize();
+    assert!(cs.is_satisfied());
+}
+
+#[test]
+fn test_rescue_ready_params_are_constants() {
+    const SIZE: usize = 3;
+    const RNUMBER: usize = 7;
+    let rng = &mut init_rng();
+    let params: CipherParams<Bn256, SIZE, RNUMBER> = generate_cipher_params(rng);
+    let ready_params = construct_ready_params(&params).unwrap();
+
+    let matrix = construct_mds_matrix_native::<Bn256, SIZE>(&params.vect_for_matrix).unwrap();
+    let inv_matrix = construct_inverse_matrix_native::<Bn256, SIZE>(&matrix).unwrap();
+    for i in 0..SIZE {
+        assert_eq!(ready_params.matrix.get_row(i).unwrap(), matrix[i]);
+        assert_eq!(ready_params.inv_matrix.get_row(i).unwrap(), inv_matrix[i]);
+    }
+    assert!(matches!(ready_params.matrix.get_row(SIZE), Err(CipherError::RowOutOfRange(SIZE))));
+    for (constants, expected) in ready_params.round_constants.iter().zip(params.round_constants.iter()) {
+        for (constant, expected) in constants.iter().zip(expected.iter()) {
+            assert!(constant.is_constant());
+            assert_eq!(constant.get_value().unwrap(), *expected);
+        }
+    }
+}
+
+#[test]
+fn test_rescue_insecure_params_are_refused() {
+    let rng = &mut init_rng();
+    assert!(generate_secure_cipher_params::<Bn256, _, 3, 7>(rng, DEFAULT_SECURITY_LEVEL).is_none());
+    assert!(generate_secure_cipher_params::<Bn256, _, 3, 29>(rng, DEFAULT_SECURITY_LEVEL).is_some());
+}
+
+#[test]
+fn test_rescue_bad_params_are_errors() {
+    let rng = &mut init_rng();
+    let mut params: CipherParams<Bn256, 3, 7> = generate_cipher_params(rng);
+    let key = test_inputs::<Bn256, 3>(rng);
+    assert!(params.check().is_ok());
+
+    params.vect_for_matrix[1][0] = params.vect_for_matrix[0][2];
+    assert!(matches!(params.check(), Err(CipherError::InvalidMdsVectors)));
+    assert!(matches!(construct_ready_params(&params), Err(CipherError::InvalidMdsVectors)));
+    assert!(matches!(rescue_encryption_native(&params, &key, &key), Err(CipherError::InvalidMdsVectors)));
+
+    params.vect_for_matrix[1].pop();
+    assert!(matches!(construct_ready_params(&params), Err(CipherError::InvalidMdsVectors)));
+    assert!(matches!(rescue_decryption_native(&params, &key, &key), Err(CipherError::InvalidMdsVectors)));
+}
+
+#[test]
+fn test_rescue_native_round_trip() {
+    test_native_round_trip::<Bn256, 3, 7>();
+    test_native_round_trip::<Bn256, 3, 8>();
+    test_native_round_trip::<Bn256, 2, 7>();
+    test_native_round_trip::<Bn256, 4, 7>();
+    test_native_round_trip::<Bn256, 8, 7>();
+}
+
+#[test]
+fn test_rescue_native_matches_circuit() {
+    test_native_matches_circuit::<Bn256, 3, 7>();
+}
+
+#[test]
+fn test_rescue_native_matches_circuit_wide_state() {
+    test_native_matches_circuit::<Bn256, 5, 7>();
+}
+
+#[test]
+fn test_rescue_native_matches_circuit_even_rounds() {
+    test_native_matches_circuit::<Bn256, 3, 4>();
+}
+
+#[test]
+fn test_rescue_setup_mode_synthesis() {
+    test_setup_mode_synthesis::<Bn256, 3, 7>();
+    test_setup_mode_synthesis::<Bn256, 3, 4>();
+}
+
+#[test]
+fn test_rescue_conditional_encryption() {
+    test_conditional_encryption::<Bn256, 3, 7>();
+}
+
+#[test]
+fn test_rescue_tweakable_cipher() {
+    test_tweakable_cipher::<Bn256, 3, 7>();
+    test_tweakable_cipher::<Bn256, 3, 4>();
+}
+
+#[test]
+fn test_rescue_key_schedule_reuse() {
+    test_key_schedule_reuse::<Bn256, 3, 7>();
+}
+
+#[test]
+fn test_rescue_known_answers() {
+    let sets: Vec<KnownAnswerSet> = serde_json::from_str(include_str!("../test_vectors/bn256.json")).unwrap();
+    assert!(!sets.is_empty());
+    for set in sets.iter() {
+        match (set.size, set.rnumber) {
+            (2, 41) => test_known_answers::<2, 41>(set),
+            (3, 29) => test_known_answers::<3, 29>(set),
+            (4, 23) => test_known_answers::<4, 23>(set),
+            (5, 19) => test_known_answers::<5, 19>(set),
+            other => panic!("no instantiation for SIZE and RNUMBER {:?}", other),
+        }
+    }
+
+    let tags: Vec<&[u8]> = sets.iter().filter_map(|set| set.tag.as_ref().map(|tag| tag.as_bytes())).collect();
+    for tag in [BN256_SIZE_2_TAG, BN256_SIZE_3_TAG, BN256_SIZE_4_TAG, BN256_SIZE_5_TAG].iter() {
+        assert!(tags.contains(tag));
+    }
+}
@@ -0,0 +1,243 @@
+#!/usr/bin/env python3
+# Regenerates bn256.json with an implementation of the cipher that is
+# independent of the Rust code. Run from this directory: python3 generate.py
+import hashlib
+import json
+
+FR = 21888242871839275222246405745257275088548364400416034343698204186575808495617
+FQ = 21888242871839275222246405745257275088696311157297823662689037894645226208583
+ALPHA = 5
+ALPHA_INV = pow(ALPHA, -1, FR - 1)
+
+# (SIZE, RNUMBER) of the default parameter sets
+SETS = [(2, 41), (3, 29), (4, 23), (5, 19)]
+CASES_PER_SET = 2
+
+# Tags of the default parameter sets in cipher_tools/defaults.rs
+DEFAULT_TAGS = {
+    (2, 41): b"Rescue_block_cipher_BN256_2_41",
+    (3, 29): b"Rescue_block_cipher_BN256_3_29",
+    (4, 23): b"Rescue_block_cipher_BN256_4_23",
+    (5, 19): b"Rescue_block_cipher_BN256_5_19",
+}
+# franklin_crypto::constants::GH_FIRST_BLOCK
+GH_FIRST_BLOCK = b"096b36a5804bfacef1691e173c366a47ff5ba84a44f26ddd7e8d9f79d5b42df0"
+MASK32 = 0xffffffff
+MASK64 = 0xffffffffffffffff
+
+
+def to_hex(x):
+    return x.to_bytes(32, "big").hex()
+
+
+class Sampler:
+    # Field elements from SHA-256 of a domain tag and a counter, reduced
+    # modulo the field characteristic.
+    def __init__(self, tag):
+        self.tag = tag
+        self.counter = 0
+
+    def element(self):
+        while True:
+            data = b"Rescue_block_cipher_KAT" + self.tag + self.counter.to_bytes(4, "big")
+            self.counter += 1
+            x = int.from_bytes(hashlib.sha256(data).digest(), "big") % FR
+            if x != 0:
+                return x
+
+    def vector(self, size):
+        return [self.element() for _ in range(size)]
+
+
+class ChaChaRng:
+    # ChaCha20 with a zero nonce as in rand 0.4, which the Rust code seeds
+    # from the tag.
+    def __init__(self, key):
+        self.state = [0x61707865, 0x3320646E, 0x79622D32, 0x6B206574] + list(key) + [0] * 4
+        self.buffer = []
+
+    @staticmethod
+    def quarter_round(x, a, b, c, d):
+        for (p, q, r, n) in ((a, b, d, 16), (c, d, b, 12), (a, b, d, 8), (c, d, b, 7)):
+            x[p] = (x[p] + x[q]) & MASK32
+            x[r] ^= x[p]
+            x[r] = ((x[r] << n) | (x[r] >> (32 - n))) & MASK32
+
+    def update(self):
+        x = self.state[:]
+        for _ in range(10):
+            for a, b, c, d in ((0, 4, 8, 12), (1, 5, 9, 13), (2, 6, 10, 14), (3, 7, 11, 15),
+                               (0, 5, 10, 15), (1, 6, 11, 12), (2, 7, 8, 13), (3, 4, 9, 14)):
+                self.quarter_round(x, a, b, c, d)
+        self.buffer = [(a + b) & MASK32 for a, b in zip(x, self.state)]
+        for i in range(12, 16):
+            self.state[i] = (self.state[i] + 1) & MASK32
+            if self.state[i] != 0:
+                break
+
+    def next_u32(self):
+        if not self.buffer:
+            self.update()
+        return self.buffer.pop(0)
+
+    def next_u64(self):
+        # rand 0.4 takes the high half first
+        hi = self.next_u32()
+        return (hi << 32) | self.next_u32()
+
+    def element(self):
+        # Fr::rand: four limbs with the top two bits cleared, rejected unless
+        # below the modulus and read in Montgomery form
+        while True:
+            limbs = [self.next_u64() for _ in range(4)]
+            limbs[3] &= MASK64 >> 2
+            raw = sum(limb << (64 * i) for i, limb in enumerate(limbs))
+            if raw < FR:
+                return raw * pow(2 ** 256, -1, FR) % FR
+
+
+def blake2s(personalization, data):
+    return hashlib.blake2s(GH_FIRST_BLOCK + data, digest_size=32, person=personalization).digest()
+
+
+def params_from_tag(size, rnumber, tag):
+    # generate_cipher_params_from_tag: MDS vectors from ChaCha seeded with
+    # Blake2s of the tag, round constants from a Blake2s stream
+    seed = blake2s(b"ResBC_md", tag)
+    rng = ChaChaRng([int.from_bytes(seed[i:i + 4], "big") for i in range(0, 32, 4)])
+    while True:
+        x = [rng.element() for _ in range(size)]
+        y = [rng.element() for _ in range(size)]
+        if len(set(x + y)) == 2 * size:
+            break
+
+    constants = []
+    nonce = 0
+    for _ in range(rnumber):
+        row = []
+        while len(row) < size:
+            c = int.from_bytes(blake2s(b"ResBC_rc", tag + nonce.to_bytes(4, "big")), "little")
+            nonce += 1
+            if 0 < c < FR:
+                row.append(c)
+        constants.append(row)
+    return x, y, constants
+
+
+def mds_matrix(x, y):
+    return [[pow((xi - yj) % FR, -1, FR) for yj in y] for xi in x]
+
+
+def mat_vec(m, v):
+    return [sum(a * b for a, b in zip(row, v)) % FR for row in m]
+
+
+def add(a, b):
+    return [(x + y) % FR for x, y in zip(a, b)]
+
+
+def sbox(v, i):
+    # odd rounds use alpha, even rounds its inverse
+    e = ALPHA if i % 2 == 1 else ALPHA_INV
+    return [pow(x, e, FR) for x in v]
+
+
+def subkeys(matrix, constants, key):
+    keys = [add(key, constants[0])]
+    for i in range(1, len(constants)):
+        keys.append(add(sbox(mat_vec(matrix, keys[-1]), i), constants[i]))
+    return keys
+
+
+def encryption_trace(matrix, keys, plaintext):
+    states = [add(plaintext, keys[0])]
+    for i in range(1, len(keys)):
+        states.append(add(sbox(mat_vec(matrix, states[-1]), i), keys[i]))
+    return states
+
+
+def vector_set(size, rnumber, tag=None):
+    if tag is None:
+        sampler = Sampler(("%d_%d" % (size, rnumber)).encode())
+        while True:
+            x, y = sampler.vector(size), sampler.vector(size)
+            if len(set(x + y)) == 2 * size:
+                break
+        constants = [sampler.vector(size) for _ in range(rnumber)]
+    else:
+        # cases for the default parameters come from their own sampler
+        sampler = Sampler(tag)
+        x, y, constants = params_from_tag(size, rnumber, tag)
+    matrix = mds_matrix(x, y)
+
+    cases = []
+    for case in range(CASES_PER_SET):
+        if case == 0:
+            key, plaintext = [0] * size, [0] * size
+        else:
+            key, plaintext = sampler.vector(size), sampler.vector(size)
+        keys = subkeys(matrix, constants, key)
+        states = encryption_trace(matrix, keys, plaintext)
+        cases.append({
+            "key": [to_hex(e) for e in key],
+            "plaintext": [to_hex(e) for e in plaintext],
+            "subkeys": [[to_hex(e) for e in k] for k in keys],
+            "round_states": [[to_hex(e) for e in s] for s in states],
+            "ciphertext": [to_hex(e) for e in states[-1]],
+        })
+
+    params = {
+        "version": 1,
+        "field_modulus": to_hex(FR),
+        "base_field_modulus": to_hex(FQ),
+        "size": size,
+        "rnumber": rnumber,
+        "alpha": ALPHA,
+        "mds_vectors": [[to_hex(e) for e in x], [to_hex(e) for e in y]],
+        "round_constants": [[to_hex(e) for e in c] for c in constants],
+    }
+    vectors = {"size": size, "rnumber": rnumber, "params": params, "cases": cases}
+    if tag is not None:
+        vectors["tag"] = tag.decode()
+    return vectors
+
+
+def self_check(vectors):
+    # decrypting with the inverse matrix must give the plaintext back
+    for s in vectors:
+        x, y = [[int(e, 16) for e in v] for v in s["params"]["mds_vectors"]]
+        matrix = mds_matrix(x, y)
+        for case in s["cases"]:
+            keys = [[int(e, 16) for e in k] for k in case["subkeys"]]
+            state = [int(e, 16) for e in case["ciphertext"]]
+            for i in range(len(keys) - 1, 0, -1):
+                state = [(a - b) % FR for a, b in zip(state, keys[i])]
+                e = ALPHA_INV if i % 2 == 1 else ALPHA
+                state = [pow(a, e, FR) for a in state]
+                state = solve(matrix, state)
+            state = [(a - b) % FR for a, b in zip(state, keys[0])]
+            assert state == [int(e, 16) for e in case["plaintext"]]
+
+
+def solve(m, v):
+    n = len(m)
+    a = [row[:] + [v[i]] for i, row in enumerate(m)]
+    for c in range(n):
+        p = next(r for r in range(c, n) if a[r][c] != 0)
+        a[c], a[p] = a[p], a[c]
+        inv = pow(a[c][c], -1, FR)
+        a[c] = [e * inv % FR for e in a[c]]
+        for r in range(n):
+            if r != c and a[r][c] != 0:
+                f = a[r][c]
+                a[r] = [(e - f * g) % FR for e, g in zip(a[r], a[c])]
+    return [a[i][n] for i in range(n)]
+
+
+if __name__ == "__main__":
+    vectors = [vector_set(size, rnumber) for size, rnumber in SETS]
+    vectors += [vector_set(size, rnumber, DEFAULT_TAGS[(size, rnumber)]) for size, rnumber in SETS]
+    self_check(vectors)
+    with open("bn256.json", "w") as f:
+        json.dump(vectors, f, indent=2)
+        f.write("\n")